@@ -1,18 +1,24 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serenity::async_trait;
-use serenity::builder::CreateMessage;
-use serenity::model::channel::Message;
-use serenity::model::gateway::Ready;
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::model::prelude::*;
 use serenity::prelude::*;
 use tokio::spawn;
-use tokio::time::{sleep, Duration};
 use tracing::{error, info};
 
-use crate::config::{BotConfig, CompletedScheduled, QuizData, ScheduledMessage};
+use crate::commands::{register_commands, run_autocomplete, run_command, run_component, run_modal};
+use crate::config::{
+    BotConfig, CompletedScheduled, CreatedEvents, QuizData, ScheduledMessage, TargetData,
+};
+use crate::delivery::{recipient_key, send_to_target, spawn_direct_messages, MessageContent};
+use crate::directory::{get_directory, resolve_channel, resolve_event, resolve_guild, Directory};
+use crate::guild_event::create_due_events;
 use crate::utils::{
-    add_attachments, add_poll, contains_answer, get_monitor_channel_id, get_target_channel,
-    get_target_guild, is_thread_started, quiz_ongoing, remove_ongoing_quiz, set_ongoing_quiz,
-    sleep_remaining_time, thread_started,
+    add_reactions, contains_answer, create_allowed_mentions, create_attachments, create_poll,
+    display_target, get_dm_recipients, get_ongoing_quiz, get_reply_reference, in_thread_of,
+    is_sending_direct_messages, is_thread_started, pin_scheduled_message, remove_ongoing_quiz,
+    save_as_completed, save_sent_message, save_target_as_completed, set_ongoing_quiz,
+    sleep_remaining_time, start_thread, thread_started, unpin_expired,
 };
 use crate::OngoingQuiz;
 
@@ -21,6 +27,22 @@ pub struct Handler;
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, _ready: Ready) {
+        // Rebuild the directory from the guilds that are already cached. On the first start, the
+        // guilds are added as their guild_create events arrive
+        {
+            let directory = get_directory(&ctx).await;
+            let mut directory = directory.write().await;
+            *directory = Directory::default();
+
+            for guild_id in ctx.cache.guilds() {
+                if let Some(guild) = ctx.cache.guild(guild_id) {
+                    directory.insert_guild(&guild);
+                }
+            }
+        }
+
+        register_commands(&ctx).await;
+
         // Prevent the thread from starting multiple times
         if !is_thread_started(&ctx).await {
             thread_started(&ctx).await;
@@ -33,13 +55,150 @@ impl EventHandler for Handler {
         }
     }
 
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Command(command) => run_command(&ctx, &command).await,
+            Interaction::Autocomplete(autocomplete) => run_autocomplete(&ctx, &autocomplete).await,
+            Interaction::Modal(modal) => run_modal(&ctx, &modal).await,
+            Interaction::Component(component) => run_component(&ctx, &component).await,
+            _ => {}
+        }
+    }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: Option<bool>) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.insert_guild(&guild);
+    }
+
+    async fn guild_update(
+        &self,
+        ctx: Context,
+        _old_data_if_available: Option<Guild>,
+        new_data: PartialGuild,
+    ) {
+        let directory = get_directory(&ctx).await;
+        directory
+            .write()
+            .await
+            .insert_guild_name(new_data.id, new_data.name);
+    }
+
+    async fn guild_delete(&self, ctx: Context, incomplete: UnavailableGuild, _full: Option<Guild>) {
+        // An unavailable guild is only temporarily unreachable and is still part of the bot
+        if incomplete.unavailable {
+            return;
+        }
+
+        let directory = get_directory(&ctx).await;
+        directory.write().await.remove_guild(incomplete.id);
+    }
+
+    async fn channel_create(&self, ctx: Context, channel: GuildChannel) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_channel(&channel);
+    }
+
+    async fn channel_update(&self, ctx: Context, _old: Option<GuildChannel>, new: GuildChannel) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_channel(&new);
+    }
+
+    async fn channel_delete(
+        &self,
+        ctx: Context,
+        channel: GuildChannel,
+        _messages: Option<Vec<Message>>,
+    ) {
+        let directory = get_directory(&ctx).await;
+        directory
+            .write()
+            .await
+            .remove_channel(channel.guild_id, channel.id);
+    }
+
+    async fn thread_create(&self, ctx: Context, thread: GuildChannel) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_channel(&thread);
+    }
+
+    async fn thread_update(&self, ctx: Context, _old: Option<GuildChannel>, new: GuildChannel) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_channel(&new);
+    }
+
+    async fn thread_delete(
+        &self,
+        ctx: Context,
+        thread: PartialGuildChannel,
+        _full_thread_data: Option<GuildChannel>,
+    ) {
+        let directory = get_directory(&ctx).await;
+        directory
+            .write()
+            .await
+            .remove_channel(thread.guild_id, thread.id);
+    }
+
+    async fn guild_role_create(&self, ctx: Context, new: Role) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_role(&new);
+    }
+
+    async fn guild_role_update(
+        &self,
+        ctx: Context,
+        _old_data_if_available: Option<Role>,
+        new: Role,
+    ) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_role(&new);
+    }
+
+    async fn guild_role_delete(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        removed_role_id: RoleId,
+        _removed_role_data_if_available: Option<Role>,
+    ) {
+        let directory = get_directory(&ctx).await;
+        directory
+            .write()
+            .await
+            .remove_role(guild_id, removed_role_id);
+    }
+
+    async fn guild_scheduled_event_create(&self, ctx: Context, event: ScheduledEvent) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_event(&event);
+    }
+
+    // Rescheduling an event in discord moves every message that is relative to it
+    async fn guild_scheduled_event_update(&self, ctx: Context, event: ScheduledEvent) {
+        let directory = get_directory(&ctx).await;
+        directory.write().await.upsert_event(&event);
+    }
+
+    async fn guild_scheduled_event_delete(&self, ctx: Context, event: ScheduledEvent) {
+        let directory = get_directory(&ctx).await;
+        directory
+            .write()
+            .await
+            .remove_event(event.guild_id, event.id);
+    }
+
     async fn message(&self, ctx: Context, new_message: Message) {
-        if !quiz_ongoing(&ctx).await {
+        // Read the quiz once as it can be stopped by a command at any time
+        let quiz = get_ongoing_quiz(&ctx).await;
+
+        if quiz.is_none() {
             return;
         }
 
-        let target_channel = get_monitor_channel_id(&ctx).await;
-        if new_message.channel_id != target_channel {
+        let target_channel = quiz.unwrap().get_monitor_channel_id();
+        if new_message.channel_id != target_channel
+            && !in_thread_of(&ctx, &new_message, target_channel).await
+        {
             return;
         }
 
@@ -54,6 +213,10 @@ impl EventHandler for Handler {
             let data = data_read.get::<OngoingQuiz>().unwrap();
             let ongoing_quiz = data.lock().await;
 
+            if ongoing_quiz.is_none() {
+                return;
+            }
+
             let quiz_data = ongoing_quiz.clone().unwrap();
             let quiz_answer = quiz_data.answer();
 
@@ -95,7 +258,15 @@ impl EventHandler for Handler {
                 info!("Quiz answer found in message: {}", new_message.content);
                 quiz_done = true;
 
-                let result = new_message.reply(&ctx, quiz_data.reply_with()).await;
+                // Only the winner can get pinged by the reply, if enabled for the quiz
+                let reply = CreateMessage::new()
+                    .content(quiz_data.reply_with())
+                    .reference_message(&new_message)
+                    .allowed_mentions(
+                        CreateAllowedMentions::new().replied_user(quiz_data.reply_ping()),
+                    );
+
+                let result = new_message.channel_id.send_message(&ctx, reply).await;
                 if let Err(e) = result {
                     error!("Failed to send the reply to the winner. This will be considered as completed regardless. Reason: {e}")
                 }
@@ -119,12 +290,14 @@ impl Handler {
         let config = bot_config.unwrap();
 
         let target_guild_name = config.get_target_guild();
+        let target_guild_id = config.get_target_guild_id();
         let target_channel_name = config.get_target_channel();
-        let pin_all = config.pin_all();
+        let target_channel_id = config.get_target_channel_id();
 
         info!(
-            "Target guild name: {}, Target channel name: {}",
-            target_guild_name, target_channel_name
+            "Target guild: {}, Target channel: {}",
+            display_target(target_guild_id, &target_guild_name),
+            display_target(target_channel_id, &target_channel_name)
         );
 
         let mut target_guild = None;
@@ -133,12 +306,20 @@ impl Handler {
         // Fetch both guild and channel in a single flow. In case the guild channel list gets
         // updated and the target channel is part of that, this should ensure the search isn't
         // happening on stale guild data
-        while target_guild.is_none() && target_channel.is_none() {
-            target_guild = get_target_guild(&ctx, &target_guild_name).await;
+        while target_channel.is_none() {
+            target_guild =
+                Self::find_guild(&ctx, target_guild_id, target_guild_name.as_deref()).await;
 
-            if let Some(guild) = &target_guild {
+            if let Some(guild_id) = target_guild {
                 info!("Target guild found");
-                target_channel = get_target_channel(&ctx, guild, &target_channel_name).await;
+                target_channel = Self::find_channel(
+                    &ctx,
+                    guild_id,
+                    target_channel_id,
+                    target_channel_name.as_deref(),
+                    config.search_archived_threads(),
+                )
+                .await;
 
                 if target_channel.is_none() {
                     error!("Target channel not found. Trying again in the next minute");
@@ -160,6 +341,9 @@ impl Handler {
             // For most thing that fails during this loop, sleep till the current minute ends and try
             // again the next minute
 
+            unpin_expired(&ctx).await;
+            create_due_events(&ctx, target_guild, config.search_archived_threads()).await;
+
             let schedule_data = ScheduledMessage::get_all_scheduled_messages();
             let completed_data = CompletedScheduled::get_completed_scheduled();
 
@@ -182,7 +366,17 @@ impl Handler {
             let now = Utc::now();
 
             for message in &scheduled {
-                if !completed.completed.contains(&message.id()) && now >= message.scheduled_at() {
+                if completed.completed.contains(&message.id()) || message.is_disabled() {
+                    continue;
+                }
+
+                if is_sending_direct_messages(&ctx, message.id()).await {
+                    continue;
+                }
+
+                let due_at = Self::due_at(&ctx, message, target_guild).await;
+
+                if due_at.is_some_and(|due_at| now >= due_at) {
                     to_handle.push(message);
                 }
             }
@@ -193,19 +387,59 @@ impl Handler {
             }
 
             for message in to_handle {
-                // Do not proceed any further if target_guild is provided but not target channel
-                if message.guild_no_channel() {
-                    error!("target_guild was provided but no target_channel was found for the scheduled message with id {}. This won't be set as completed.", message.id());
+                // Do not proceed any further if the fields of the message cannot be used together
+                if let Err(e) = message.validate() {
+                    error!(
+                        "{e} for the scheduled message with id {}. This won't be set as completed.",
+                        message.id()
+                    );
                     continue;
                 }
 
-                let mut to_send = CreateMessage::new().content(message.message());
+                let mut content = message.message();
+
+                // Add the link of the created guild event. The event must be created before the
+                // message can be sent
+                if let Some(event_id) = message.link_event {
+                    let created_event = CreatedEvents::get_created_events()
+                        .map(|created| created.get_created(event_id));
+
+                    match created_event {
+                        Ok(Some(created_event)) => {
+                            content = format!("{content}\n{}", created_event.link());
+                        }
+                        Ok(None) => {
+                            error!("The event with id {event_id} has not been created yet for the scheduled message with id {}. This won't be set as completed.", message.id());
+                            continue;
+                        }
+                        Err(e) => {
+                            error!("Failed to read created event data for the scheduled message with id {}. This won't be set as completed. Reason: {e}", message.id());
+                            continue;
+                        }
+                    }
+                }
+
+                let mut to_send = if let Some(title) = &message.embed_title {
+                    MessageContent::new_embed(title.clone(), content)
+                } else {
+                    MessageContent::new(content)
+                };
+
+                if message.silent.unwrap_or(config.silent_all()) {
+                    to_send.flags |= MessageFlags::SUPPRESS_NOTIFICATIONS;
+                }
+
+                if message.suppress_embeds.unwrap_or_default() {
+                    to_send.flags |= MessageFlags::SUPPRESS_EMBEDS;
+                }
+
+                to_send.tts = message.tts.unwrap_or_default();
 
                 // Check for poll message, if any, add it to the message that will be sent
                 if let Some(id) = message.poll_id {
-                    let add_poll_result = add_poll(to_send, id);
+                    let poll_result = create_poll(id);
 
-                    if let Err(e) = add_poll_result {
+                    if let Err(e) = poll_result {
                         error!(
                             "Failed to add poll to the scheduled message with id {}. Reason: {e}",
                             message.id()
@@ -213,7 +447,7 @@ impl Handler {
                         continue;
                     }
 
-                    to_send = add_poll_result.unwrap();
+                    to_send.poll = Some(poll_result.unwrap());
                 }
 
                 // Check for attachments, if any, add it to the message that will be sent
@@ -221,20 +455,25 @@ impl Handler {
                     if message.poll_id.is_some() {
                         error!("Cannot add attachments to a poll message. The attachments will be ignored.");
                     } else {
-                        let add_attachments_result = add_attachments(to_send, locations).await;
+                        let attachments_result = create_attachments(locations).await;
 
-                        if let Err(e) = add_attachments_result {
+                        if let Err(e) = attachments_result {
                             error!("Failed to add attachments to the scheduled message with id {}. Reason: {e}", message.id());
                             continue;
                         }
 
-                        to_send = add_attachments_result.unwrap();
+                        to_send.attachments = attachments_result.unwrap();
                     }
                 }
 
                 // Check if the message is quiz type. If yes, get the quiz data
                 let mut quiz_data = None;
                 if let Some(id) = message.quiz_id {
+                    if message.is_direct_message() {
+                        error!("A quiz cannot be sent as direct messages for the scheduled message with id {}. This won't be set as completed.", message.id());
+                        continue;
+                    }
+
                     let quiz_data_result = QuizData::get_quiz_data(id);
 
                     if let Err(e) = quiz_data_result {
@@ -252,118 +491,487 @@ impl Handler {
                         continue;
                     }
 
+                    // The thread is only started after the message is sent so it must be known
+                    // beforehand whether there will be any thread to monitor
+                    if quiz.monitor_thread()
+                        && message.thread.is_none()
+                        && message.forum_title.is_none()
+                    {
+                        error!("monitor_thread was enabled for the quiz with id {} but no thread was set for the scheduled message with id {}. This won't be set as completed.", quiz.id(), message.id());
+                        continue;
+                    }
+
                     // If a different channel is set for monitoring, try to find that or set the
                     // global channel as the channel to monitor
                     // This is done before the quiz message is sent so the bot doesn't fail later when
                     // trying to monitor for the answer
-                    if let Some(new_channel_name) = &quiz.monitor_channel {
-                        let mut guild_to_check = target_guild.clone();
-
-                        if let Some(new_guild_name) = &quiz.monitor_guild {
-                            if let Some(new_guild) = get_target_guild(&ctx, new_guild_name).await {
+                    if quiz.has_monitor_channel() {
+                        let mut guild_to_check = target_guild;
+
+                        if quiz.monitor_guild.is_some() || quiz.monitor_guild_id.is_some() {
+                            if let Some(new_guild) = Self::find_guild(
+                                &ctx,
+                                quiz.monitor_guild_id,
+                                quiz.monitor_guild.as_deref(),
+                            )
+                            .await
+                            {
                                 guild_to_check = new_guild;
                             } else {
-                                error!("Failed to find the {new_guild_name} guild for the quiz with id {}. This won't be set as completed.", quiz.id());
+                                error!("Failed to find the {} guild for the quiz with id {}. This won't be set as completed.", display_target(quiz.monitor_guild_id, &quiz.monitor_guild), quiz.id());
                                 continue;
                             }
                         }
 
-                        if let Some((channel_id, _channel)) =
-                            get_target_channel(&ctx, &guild_to_check, new_channel_name).await
+                        if let Some(channel) = Self::find_channel(
+                            &ctx,
+                            guild_to_check,
+                            quiz.monitor_channel_id,
+                            quiz.monitor_channel.as_deref(),
+                            config.search_archived_threads(),
+                        )
+                        .await
                         {
-                            quiz.set_monitor_channel_id(channel_id)
+                            quiz.set_monitor_channel_id(channel.id)
                         } else {
-                            error!("Failed to find the {new_channel_name} channel for the quiz with id {}. This won't be set as completed.", quiz.id());
+                            error!("Failed to find the {} channel for the quiz with id {}. This won't be set as completed.", display_target(quiz.monitor_channel_id, &quiz.monitor_channel), quiz.id());
                             continue;
                         }
                     } else {
-                        quiz.set_monitor_channel_id(target_channel.0)
+                        quiz.set_monitor_channel_id(target_channel.id)
                     }
                     quiz_data = Some(quiz)
                 }
 
-                let mut send_to_channel = None;
-
-                // If target channel is provided for this schedule message, try to find it
-                // If target guild is provided, search for the target channel in that specific
-                // guild.
-                if let Some(new_channel_name) = &message.target_channel {
-                    let mut guild_to_check = target_guild.clone();
-
-                    if let Some(new_guild_name) = &message.target_guild {
-                        if let Some(new_guild) = get_target_guild(&ctx, new_guild_name).await {
+                // Direct messages are sent to each recipient instead of a channel. Failures of
+                // individual recipients do not stop the message from being marked as completed
+                if message.is_direct_message() {
+                    let mut guild_to_check = target_guild;
+
+                    if message.has_target_guild() {
+                        if let Some(new_guild) = Self::find_guild(
+                            &ctx,
+                            message.target_guild_id,
+                            message.target_guild.as_deref(),
+                        )
+                        .await
+                        {
                             guild_to_check = new_guild;
                         } else {
-                            error!("Failed to find the {new_guild_name} guild for the scheduled message with id {}. This won't be set as completed.", message.id());
+                            error!("Failed to find the {} guild for the scheduled message with id {}. This won't be set as completed.", display_target(message.target_guild_id, &message.target_guild), message.id());
                             continue;
                         }
                     }
 
-                    if let Some((_channel_id, channel)) =
-                        get_target_channel(&ctx, &guild_to_check, new_channel_name).await
-                    {
-                        send_to_channel = Some(channel);
-                    } else {
-                        error!("Failed to find the {new_channel_name} channel for the scheduled message with id {}. This won't be set as completed.", message.id());
+                    let recipients_result = get_dm_recipients(&ctx, guild_to_check, message).await;
+
+                    if let Err(e) = recipients_result {
+                        error!("Failed to get the direct message recipients for the scheduled message with id {}. This won't be set as completed. Reason: {e}", message.id());
                         continue;
                     }
-                }
 
-                // Send crafted message to the global target_channel in the bot config or the new channel in the
-                // scheduled message itself, if provided
-                let result = if let Some(channel) = send_to_channel {
-                    channel.send_message(&ctx, to_send).await
-                } else {
-                    target_channel.1.send_message(&ctx, to_send).await
-                };
+                    // Recipients that got the message before the bot stopped are not sent it again
+                    let recipients = recipients_result
+                        .unwrap()
+                        .into_iter()
+                        .filter(|user_id| {
+                            !completed.target_completed(message.id(), &recipient_key(*user_id))
+                        })
+                        .collect::<Vec<_>>();
+
+                    let mentions_result = create_allowed_mentions(
+                        &ctx,
+                        guild_to_check,
+                        message
+                            .allowed_mentions
+                            .as_ref()
+                            .unwrap_or(&config.allowed_mentions()),
+                    )
+                    .await;
+
+                    if let Err(e) = mentions_result {
+                        error!("Failed to set the allowed mentions for the scheduled message with id {}. This won't be set as completed. Reason: {e}", message.id());
+                        continue;
+                    }
 
-                if let Err(e) = result {
-                    info!("Failed to send scheduled message with id {}. This won't be set as completed. Reason: {e}", message.id());
+                    to_send.allowed_mentions = Some(mentions_result.unwrap());
+
+                    spawn_direct_messages(&ctx, to_send, message.id(), recipients).await;
                     continue;
                 }
 
-                info!("Scheduled message with id {} was sent", message.id());
+                // Send crafted message to every target of the scheduled message or to the global
+                // target_channel in the bot config if there are none. With multiple targets, each
+                // one is marked as completed on its own so only the failed ones are retried
+                let targets = message.get_targets();
+                let mut delivered = 0;
+                let mut failed = 0;
+
+                if targets.is_empty() {
+                    if Self::deliver(
+                        &ctx,
+                        &config,
+                        &target_channel,
+                        &to_send,
+                        message,
+                        &mut quiz_data,
+                    )
+                    .await
+                    {
+                        delivered += 1;
+                    } else {
+                        failed += 1;
+                    }
+                }
 
-                let sent_message = result.unwrap();
+                for target in &targets {
+                    let key = target.key();
 
-                let mut pin_message = pin_all;
+                    if completed.target_completed(message.id(), &key) {
+                        continue;
+                    }
 
-                if let Some(to_pin) = message.to_pin {
-                    pin_message = to_pin;
-                }
+                    let channel = Self::resolve_target(
+                        &ctx,
+                        target_guild,
+                        target,
+                        message.id(),
+                        config.search_archived_threads(),
+                    )
+                    .await;
+
+                    if channel.is_none() {
+                        failed += 1;
+                        continue;
+                    }
 
-                if pin_message {
-                    let pin_result = sent_message.pin(&ctx).await;
-                    if let Err(e) = pin_result {
-                        error!("Failed to pin scheduled message with id {}. This message will be marked as completed regardless. Reason: {e}", message.id());
+                    if !Self::deliver(
+                        &ctx,
+                        &config,
+                        &channel.unwrap(),
+                        &to_send,
+                        message,
+                        &mut quiz_data,
+                    )
+                    .await
+                    {
+                        failed += 1;
+                        continue;
+                    }
+
+                    delivered += 1;
+
+                    if targets.len() > 1 {
+                        save_target_as_completed(&ctx, &mut completed, message.id(), key).await;
                     }
                 }
 
                 // Keep track of the quiz data if this is one.
                 // Will overwrite if an existing quiz is ongoing
-                if let Some(data) = quiz_data {
-                    set_ongoing_quiz(&ctx, data).await;
+                if delivered > 0 {
+                    if let Some(data) = quiz_data {
+                        set_ongoing_quiz(&ctx, data).await;
+                    }
                 }
 
-                completed.add_new_completed(message.id());
+                if failed > 0 {
+                    if targets.len() > 1 {
+                        error!("Scheduled message with id {} failed to be sent to {failed} of {} targets. Only the failed targets will be tried again.", message.id(), targets.len());
+                    }
+                    continue;
+                }
+
+                save_as_completed(&ctx, &mut completed, message.id()).await;
+            }
+
+            sleep_remaining_time().await;
+        }
+    }
+
+    /// The time the scheduled message should be sent at. For a message relative to a guild event,
+    /// this is calculated from the current start time of the event
+    async fn due_at(
+        ctx: &Context,
+        message: &ScheduledMessage,
+        target_guild: GuildId,
+    ) -> Option<DateTime<Utc>> {
+        if let Err(e) = message.validate_time() {
+            error!(
+                "{e} for the scheduled message with id {}. This won't be set as completed.",
+                message.id()
+            );
+            return None;
+        }
+
+        if message.relative_to.is_none() {
+            return message.scheduled_at();
+        }
+
+        let relative = message.relative_to.as_ref().unwrap();
+
+        let mut guild_to_check = target_guild;
+
+        if relative.has_guild() {
+            if let Some(new_guild) =
+                Self::find_guild(ctx, relative.guild_id, relative.guild.as_deref()).await
+            {
+                guild_to_check = new_guild;
+            } else {
+                error!("Failed to find the {} guild of the event for the scheduled message with id {}. This won't be set as completed.", display_target(relative.guild_id, &relative.guild), message.id());
+                return None;
+            }
+        }
+
+        match resolve_event(
+            ctx,
+            guild_to_check,
+            relative.event_id,
+            relative.event.as_deref(),
+        )
+        .await
+        {
+            Ok(Some(event)) => {
+                let time = relative
+                    .offset()
+                    .and_then(|offset| event.start_time.checked_add_signed(offset));
+
+                if time.is_none() {
+                    error!("The time of the scheduled message with id {} is out of range from the start of its event. This won't be set as completed.", message.id());
+                }
+
+                time
+            }
+            Ok(None) => {
+                error!("Failed to find the {} event for the scheduled message with id {}. This won't be set as completed.", display_target(relative.event_id, &relative.event), message.id());
+                None
+            }
+            Err(e) => {
+                error!("Failed to fetch the events of the guild {guild_to_check}. Reason: {e}");
+                None
+            }
+        }
+    }
+
+    /// Find a guild by its id or name. Failing to fetch the guild list is logged separately from
+    /// the guild not being found
+    async fn find_guild(
+        ctx: &Context,
+        guild_id: Option<GuildId>,
+        guild_name: Option<&str>,
+    ) -> Option<GuildId> {
+        match resolve_guild(ctx, guild_id, guild_name).await {
+            Ok(guild) => guild,
+            Err(e) => {
+                error!("Failed to fetch the guild list. Reason: {e}");
+                None
+            }
+        }
+    }
+
+    /// Find a channel in the guild by its id or name. Failing to fetch the channel list is logged
+    /// separately from the channel not being found
+    async fn find_channel(
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+        channel_name: Option<&str>,
+        search_archived: bool,
+    ) -> Option<GuildChannel> {
+        match resolve_channel(ctx, guild_id, channel_id, channel_name, search_archived).await {
+            Ok(channel) => channel,
+            Err(e) => {
+                error!("Failed to fetch the channel list of the guild {guild_id}. Reason: {e}");
+                None
+            }
+        }
+    }
+
+    /// Find the channel of a scheduled message target. If the target has no guild, the channel is
+    /// searched in the global target_guild in the bot config
+    async fn resolve_target(
+        ctx: &Context,
+        target_guild: GuildId,
+        target: &TargetData,
+        id: u32,
+        search_archived: bool,
+    ) -> Option<GuildChannel> {
+        let mut guild_to_check = target_guild;
+
+        if target.guild.is_some() || target.guild_id.is_some() {
+            if let Some(new_guild) =
+                Self::find_guild(ctx, target.guild_id, target.guild.as_deref()).await
+            {
+                guild_to_check = new_guild;
+            } else {
+                error!("Failed to find the {} guild for the scheduled message with id {id}. This won't be set as completed.", display_target(target.guild_id, &target.guild));
+                return None;
+            }
+        }
+
+        if target.channel.is_none() && target.channel_id.is_none() {
+            error!("A target without channel or channel_id was found for the scheduled message with id {id}. This won't be set as completed.");
+            return None;
+        }
+
+        if let Some(channel) = Self::find_channel(
+            ctx,
+            guild_to_check,
+            target.channel_id,
+            target.channel.as_deref(),
+            search_archived,
+        )
+        .await
+        {
+            Some(channel)
+        } else {
+            error!("Failed to find the {} channel for the scheduled message with id {id}. This won't be set as completed.", target.channel_name());
+            None
+        }
+    }
+
+    /// Send a scheduled message to a channel along with starting a thread, pinning and crossposting
+    /// the sent message, as requested. Returns whether the message was sent
+    async fn deliver(
+        ctx: &Context,
+        config: &BotConfig,
+        channel: &GuildChannel,
+        to_send: &MessageContent,
+        message: &ScheduledMessage,
+        quiz_data: &mut Option<QuizData>,
+    ) -> bool {
+        // Roles in the allowed mentions are different in every guild so they are set per target
+        let mentions_result = create_allowed_mentions(
+            ctx,
+            channel.guild_id,
+            message
+                .allowed_mentions
+                .as_ref()
+                .unwrap_or(&config.allowed_mentions()),
+        )
+        .await;
+
+        if let Err(e) = mentions_result {
+            error!("Failed to set the allowed mentions for the scheduled message with id {} in {}. This won't be set as completed. Reason: {e}", message.id(), channel.name);
+            return false;
+        }
+
+        let mut to_send = to_send.clone();
+        to_send.allowed_mentions = Some(mentions_result.unwrap());
 
-                // Try to save the scheduled id as completed 3 times. If failed, exit the bot
-                for num in 0..3 {
-                    let save_result = completed.save_completed_scheduled();
-                    if save_result.is_ok() {
-                        break;
+        // Replies are only possible in normal channels and threads. If the referenced message
+        // cannot be found, the message is sent without replying
+        if message.reply_to.is_some() || message.reply_to_schedule.is_some() {
+            if channel.kind == ChannelType::Forum || message.webhook.is_some() {
+                error!("Scheduled message with id {} cannot reply to a message as a forum post or over a webhook. It will be sent without replying.", message.id());
+            } else {
+                match get_reply_reference(channel.id, message) {
+                    Ok(reference) => to_send.reference = reference,
+                    Err(e) => {
+                        error!("Failed to find the message to reply to for the scheduled message with id {} in {}. It will be sent without replying. Reason: {e}", message.id(), channel.name);
                     }
-                    if num == 2 {
-                        error!("Failed to save the scheduled message id as completed. This is a fatal error and the bot will be exited. \
-                            The scheduled message was sent successfully but the id number could not be saved as completed. \
-                            Before the next run, completed.json must be updated manually otherwise the same scheduled message will be sent again.\n\nReason: {}", save_result.unwrap_err());
-                        std::process::exit(1)
+                }
+            }
+        }
+
+        let result = send_to_target(ctx, channel, &to_send, message).await;
+
+        if let Err(e) = result {
+            info!("Failed to send scheduled message with id {} to {}. This won't be set as completed. Reason: {e}", message.id(), channel.name);
+            return false;
+        }
+
+        info!(
+            "Scheduled message with id {} was sent to {}",
+            message.id(),
+            channel.name
+        );
+
+        let sent_message = result.unwrap();
+
+        if let Err(e) = save_sent_message(&sent_message, message.id()) {
+            error!("Failed to save the sent message of the scheduled message with id {}. Later messages won't be able to reply to it. Reason: {e}", message.id());
+        }
+
+        // Start a thread from the sent message if requested. The monitored channel of the
+        // quiz gets replaced by the thread if the quiz is set to monitor it.
+        // A forum post is already a thread on its own so no new thread is started
+        if channel.kind == ChannelType::Forum {
+            if message.thread.is_some() {
+                error!("Scheduled message with id {} was sent as a forum post which is already a thread. The thread data will be ignored.", message.id());
+            }
+
+            if let Some(quiz) = quiz_data.as_mut() {
+                if quiz.monitor_thread() {
+                    quiz.set_monitor_channel_id(sent_message.channel_id);
+                }
+            }
+        } else if message.thread.is_some() {
+            let thread_result = start_thread(ctx, &sent_message, message).await;
+
+            match thread_result {
+                Ok(thread) => {
+                    info!(
+                        "Thread started for scheduled message with id {}",
+                        message.id()
+                    );
+                    if let Some(quiz) = quiz_data.as_mut() {
+                        if quiz.monitor_thread() {
+                            quiz.set_monitor_channel_id(thread.id);
+                        }
                     }
-                    sleep(Duration::from_secs(2)).await;
+                }
+                Err(e) => {
+                    error!("Failed to start a thread for scheduled message with id {}. This message will be marked as completed regardless. Reason: {e}", message.id());
                 }
             }
+        }
 
-            sleep_remaining_time().await;
+        if let Some(reactions) = &message.reactions {
+            add_reactions(
+                ctx,
+                &sent_message,
+                channel.guild_id,
+                reactions,
+                message.id(),
+            )
+            .await;
         }
+
+        let mut pin_message = config.pin_all();
+
+        if let Some(to_pin) = message.to_pin {
+            pin_message = to_pin;
+        }
+
+        if pin_message {
+            let pin_result = pin_scheduled_message(
+                ctx,
+                &sent_message,
+                message.id(),
+                message.unpin_after,
+                config.unpin_oldest_at(),
+            )
+            .await;
+            if let Err(e) = pin_result {
+                error!("Failed to pin scheduled message with id {}. This message will be marked as completed regardless. Reason: {e}", message.id());
+            }
+        }
+
+        // Publish the message to the following channels if this is an announcement channel.
+        // Only an explicit crosspost on the scheduled message is reported for other channel types
+        if message.crosspost.unwrap_or(config.crosspost_all()) {
+            if channel.kind == ChannelType::News {
+                let crosspost_result = sent_message.crosspost(ctx).await;
+                if let Err(e) = crosspost_result {
+                    error!("Failed to crosspost scheduled message with id {}. This message will be marked as completed regardless. Reason: {e}", message.id());
+                } else {
+                    info!("Scheduled message with id {} was crossposted", message.id());
+                }
+            } else if message.crosspost.is_some() {
+                error!("Scheduled message with id {} was not sent to an announcement channel and cannot be crossposted.", message.id());
+            }
+        }
+
+        true
     }
 }
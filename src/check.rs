@@ -0,0 +1,309 @@
+use chrono::Utc;
+use std::collections::HashSet;
+use std::path::Path;
+use tracing::{error, info};
+
+use crate::config::{
+    BotConfig, CompletedScheduled, CreatedEvents, EventData, PollData, QuizData, ScheduledMessage,
+};
+use crate::utils::{ARCHIVE_DURATIONS, MAX_POLL_MINUTES, MAX_SLOWMODE_SECONDS};
+
+const MAX_ATTACHMENTS: usize = 10;
+const MAX_POLL_QUESTION_LENGTH: usize = 300;
+const MAX_POLL_ANSWERS: usize = 10;
+const MAX_POLL_ANSWER_LENGTH: usize = 55;
+
+/// Every problem found in the config files, along with the file it was found in
+#[derive(Default)]
+struct Problems {
+    problems: Vec<String>,
+}
+
+impl Problems {
+    /// A config file that could not be loaded. The error already names the file
+    fn add_error(&mut self, error: String) {
+        self.problems.push(error);
+    }
+
+    fn add(&mut self, file: &str, problem: String) {
+        self.problems.push(format!("{file}: {problem}"));
+    }
+}
+
+/// Load every config file and report all the problems that would otherwise only show up once the
+/// bot is running. Nothing is sent to discord. Returns the number of problems found
+pub fn check_config() -> usize {
+    let mut problems = Problems::default();
+
+    if let Err(e) = BotConfig::get_config() {
+        problems.add_error(e.to_string());
+    }
+
+    let polls = PollData::get_all_polls().unwrap_or_else(|e| {
+        problems.add_error(e.to_string());
+        Vec::new()
+    });
+
+    let quizzes = QuizData::get_all_quiz_data().unwrap_or_else(|e| {
+        problems.add_error(e.to_string());
+        Vec::new()
+    });
+
+    let events = EventData::get_all_events().unwrap_or_else(|e| {
+        problems.add_error(e.to_string());
+        Vec::new()
+    });
+
+    if let Err(e) = CreatedEvents::get_created_events() {
+        problems.add_error(e.to_string());
+    }
+
+    let completed = CompletedScheduled::get_completed_scheduled().unwrap_or_else(|e| {
+        problems.add_error(e.to_string());
+        CompletedScheduled::default()
+    });
+
+    let messages = ScheduledMessage::get_all_scheduled_messages().unwrap_or_else(|e| {
+        problems.add_error(e.to_string());
+        Vec::new()
+    });
+
+    check_polls(&mut problems, &polls);
+    check_quizzes(&mut problems, &quizzes);
+    check_events(&mut problems, &events);
+    check_schedule(
+        &mut problems,
+        &messages,
+        &completed,
+        &polls,
+        &quizzes,
+        &events,
+    );
+
+    for problem in &problems.problems {
+        error!("{problem}");
+    }
+
+    if problems.problems.is_empty() {
+        info!("No problems found in the config files");
+    } else {
+        error!(
+            "Found {} problems in the config files",
+            problems.problems.len()
+        );
+    }
+
+    problems.problems.len()
+}
+
+/// The ids that are used more than once, in the order they are found
+fn duplicate_ids(ids: impl Iterator<Item = u32>) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for id in ids {
+        if !seen.insert(id) && !duplicates.contains(&id) {
+            duplicates.push(id);
+        }
+    }
+
+    duplicates
+}
+
+fn check_polls(problems: &mut Problems, polls: &[PollData]) {
+    for id in duplicate_ids(polls.iter().map(PollData::id)) {
+        problems.add("poll.json", format!("Poll id {id} is used more than once"));
+    }
+
+    for poll in polls {
+        let id = poll.id();
+
+        if poll.question().chars().count() > MAX_POLL_QUESTION_LENGTH {
+            problems.add(
+                "poll.json",
+                format!(
+                    "Poll {id} has a question longer than {MAX_POLL_QUESTION_LENGTH} characters"
+                ),
+            );
+        }
+
+        if poll.answers().len() < 2 || poll.answers().len() > MAX_POLL_ANSWERS {
+            problems.add(
+                "poll.json",
+                format!("Poll {id} must have between 2 and {MAX_POLL_ANSWERS} answers"),
+            );
+        }
+
+        if poll
+            .answers()
+            .iter()
+            .any(|answer| answer.chars().count() > MAX_POLL_ANSWER_LENGTH)
+        {
+            problems.add(
+                "poll.json",
+                format!("Poll {id} has an answer longer than {MAX_POLL_ANSWER_LENGTH} characters"),
+            );
+        }
+
+        if poll.duration_minutes() > MAX_POLL_MINUTES {
+            problems.add(
+                "poll.json",
+                format!("Poll {id} lasts longer than {MAX_POLL_MINUTES} minutes"),
+            );
+        }
+    }
+}
+
+fn check_quizzes(problems: &mut Problems, quizzes: &[QuizData]) {
+    for id in duplicate_ids(quizzes.iter().map(QuizData::id)) {
+        problems.add("quiz.json", format!("Quiz id {id} is used more than once"));
+    }
+
+    for quiz in quizzes {
+        if quiz.guild_no_channel() {
+            problems.add(
+                "quiz.json",
+                format!(
+                    "Quiz {} has monitor_guild but no monitor_channel",
+                    quiz.id()
+                ),
+            );
+        }
+    }
+}
+
+fn check_events(problems: &mut Problems, events: &[EventData]) {
+    for id in duplicate_ids(events.iter().map(EventData::id)) {
+        problems.add(
+            "events.json",
+            format!("Event id {id} is used more than once"),
+        );
+    }
+
+    for event in events {
+        let id = event.id();
+
+        if event.has_voice_channel() == event.location.is_some() {
+            problems.add(
+                "events.json",
+                format!("Event {id} must have exactly one of location or voice_channel"),
+            );
+        }
+
+        if event.location.is_some() && event.end_at().is_none() {
+            problems.add(
+                "events.json",
+                format!("Event {id} has a location but no end_at"),
+            );
+        }
+
+        if let Some(image) = &event.image {
+            if !Path::new(image).is_file() {
+                problems.add(
+                    "events.json",
+                    format!("Event {id} has an image {image} that does not exist"),
+                );
+            }
+        }
+    }
+}
+
+fn check_schedule(
+    problems: &mut Problems,
+    messages: &[ScheduledMessage],
+    completed: &CompletedScheduled,
+    polls: &[PollData],
+    quizzes: &[QuizData],
+    events: &[EventData],
+) {
+    for id in duplicate_ids(messages.iter().map(ScheduledMessage::id)) {
+        problems.add(
+            "schedule.json",
+            format!("Scheduled message id {id} is used more than once"),
+        );
+    }
+
+    let now = Utc::now();
+
+    for message in messages {
+        let id = message.id();
+        let mut add = |problem: String| {
+            problems.add(
+                "schedule.json",
+                format!("Scheduled message {id}: {problem}"),
+            );
+        };
+
+        if let Err(e) = message.validate() {
+            add(e.to_string());
+        }
+
+        if let Some(poll_id) = message.poll_id {
+            if !polls.iter().any(|poll| poll.id() == poll_id) {
+                add(format!("poll_id {poll_id} was not found in poll.json"));
+            }
+        }
+
+        if let Some(quiz_id) = message.quiz_id {
+            if !quizzes.iter().any(|quiz| quiz.id() == quiz_id) {
+                add(format!("quiz_id {quiz_id} was not found in quiz.json"));
+            }
+
+            if message.is_direct_message() {
+                add("A quiz cannot be sent as direct messages".to_string());
+            }
+        }
+
+        if let Some(event_id) = message.link_event {
+            if !events.iter().any(|event| event.id() == event_id) {
+                add(format!(
+                    "link_event {event_id} was not found in events.json"
+                ));
+            }
+        }
+
+        if let Some(reply_id) = message.reply_to_schedule {
+            if !messages.iter().any(|other| other.id() == reply_id) {
+                add(format!(
+                    "reply_to_schedule {reply_id} was not found in schedule.json"
+                ));
+            }
+        }
+
+        if let Some(attachments) = &message.attachments {
+            if attachments.len() > MAX_ATTACHMENTS {
+                add(format!("At most {MAX_ATTACHMENTS} attachments can be sent"));
+            }
+
+            if message.poll_id.is_some() {
+                add("Attachments cannot be sent along with a poll".to_string());
+            }
+
+            for location in attachments {
+                if !Path::new(location).is_file() {
+                    add(format!("Attachment {location} does not exist"));
+                }
+            }
+        }
+
+        if let Some(thread) = &message.thread {
+            if !ARCHIVE_DURATIONS.contains(&thread.auto_archive_minutes()) {
+                add(format!(
+                    "thread auto_archive_minutes must be one of {ARCHIVE_DURATIONS:?}"
+                ));
+            }
+
+            if thread.slowmode_seconds() > MAX_SLOWMODE_SECONDS {
+                add(format!(
+                    "thread slowmode_seconds can be at most {MAX_SLOWMODE_SECONDS}"
+                ));
+            }
+        }
+
+        let pending = !completed.completed.contains(&id) && !message.is_disabled();
+
+        if pending && message.scheduled_at().is_some_and(|time| time < now) {
+            add("scheduled_at is in the past but the message was not sent yet".to_string());
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, TimeDelta, Utc};
+use serenity::builder::{
+    CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+use super::schedule::{display_channel, display_time};
+use super::{check_not_past, find_channel_input, lock_schedule, modal_value, parse_time};
+use crate::config::{CompletedScheduled, ScheduledMessage};
+use crate::ComposeDrafts;
+
+pub const MODAL_ID: &str = "schedule_compose";
+pub const CONFIRM_ID: &str = "schedule_compose_confirm";
+pub const CANCEL_ID: &str = "schedule_compose_cancel";
+const MAX_BODY_LENGTH: u16 = 4000;
+const MAX_TITLE_LENGTH: u16 = 256;
+/// Discord stops accepting responses to an interaction after 15 minutes, so the buttons of an
+/// older preview cannot be used anymore
+const DRAFT_LIFETIME: TimeDelta = TimeDelta::minutes(15);
+
+/// A composed message waiting for its preview to be confirmed
+pub struct ComposeDraft {
+    message: ScheduledMessage,
+    created_at: DateTime<Utc>,
+}
+
+impl ComposeDraft {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.created_at > DRAFT_LIFETIME
+    }
+}
+
+/// Open the modal to write a scheduled message that is too long for the options of a slash command
+pub async fn open(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let message = CreateInputText::new(InputTextStyle::Paragraph, "Message", "message")
+        .max_length(MAX_BODY_LENGTH)
+        .required(true);
+
+    let time = CreateInputText::new(InputTextStyle::Short, "Time in UTC", "time")
+        .placeholder("2024-06-01 12:00")
+        .required(true);
+
+    let channel = CreateInputText::new(InputTextStyle::Short, "Channel", "channel")
+        .placeholder("Name, ID or mention. Defaults to the target channel")
+        .required(false);
+
+    let embed_title = CreateInputText::new(InputTextStyle::Short, "Embed title", "embed_title")
+        .placeholder("Sends the message as an embed with this title")
+        .max_length(MAX_TITLE_LENGTH)
+        .required(false);
+
+    let modal = CreateModal::new(MODAL_ID, "Compose scheduled message").components(vec![
+        CreateActionRow::InputText(message),
+        CreateActionRow::InputText(time),
+        CreateActionRow::InputText(channel),
+        CreateActionRow::InputText(embed_title),
+    ]);
+
+    command
+        .create_response(ctx, CreateInteractionResponse::Modal(modal))
+        .await?;
+    Ok(())
+}
+
+/// Check the submitted modal and show a preview of the message. Nothing is saved until the
+/// preview is confirmed
+pub async fn submit(ctx: &Context, modal: &ModalInteraction) -> Result<(), Error> {
+    let guild_id = modal
+        .guild_id
+        .ok_or_else(|| anyhow!("Messages can only be composed in a guild"))?;
+
+    let message = modal_value(modal, "message").unwrap_or_default();
+    let scheduled_at = parse_time(&modal_value(modal, "time").unwrap_or_default())?;
+
+    if message.trim().is_empty() {
+        return Err(anyhow!("message cannot be empty"));
+    }
+
+    // The id is only given once the message is confirmed so it cannot be taken in the meantime
+    let mut scheduled = ScheduledMessage::new(0, message, scheduled_at);
+
+    if let Some(input) = modal_value(modal, "channel").filter(|input| !input.trim().is_empty()) {
+        let channel = find_channel_input(ctx, guild_id, &input).await?;
+
+        if channel.kind == ChannelType::Forum {
+            return Err(anyhow!(
+                "Forum channels need a forum_title which cannot be set here"
+            ));
+        }
+
+        scheduled.target_guild_id = Some(guild_id);
+        scheduled.target_channel_id = Some(channel.id);
+    }
+
+    scheduled.embed_title = modal_value(modal, "embed_title")
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    scheduled.validate()?;
+
+    let status = format!(
+        "This will be sent {} to {}. Confirm to schedule it",
+        display_time(&scheduled),
+        display_channel(&scheduled)
+    );
+
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{CONFIRM_ID}:{}", modal.id))
+            .label("Confirm")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{CANCEL_ID}:{}", modal.id))
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ]);
+
+    let response = render(&scheduled, status)
+        .components(vec![buttons])
+        .ephemeral(true);
+
+    let now = Utc::now();
+    let drafts = get_drafts(ctx).await;
+    let mut drafts = drafts.lock().await;
+
+    // Drafts that were never confirmed or cancelled are dropped here
+    drafts.retain(|_id, draft| !draft.is_expired(now));
+    drafts.insert(
+        modal.id,
+        ComposeDraft {
+            message: scheduled,
+            created_at: now,
+        },
+    );
+    drop(drafts);
+
+    modal
+        .create_response(ctx, CreateInteractionResponse::Message(response))
+        .await?;
+    Ok(())
+}
+
+/// Save the draft of the clicked preview as a new scheduled message
+pub async fn confirm(ctx: &Context, component: &ComponentInteraction) -> Result<(), Error> {
+    let draft_id = parse_draft_id(&component.data.custom_id)?;
+
+    let draft = get_drafts(ctx)
+        .await
+        .lock()
+        .await
+        .remove(&draft_id)
+        .filter(|draft| !draft.is_expired(Utc::now()));
+    let mut scheduled = draft
+        .ok_or_else(|| anyhow!("This draft no longer exists. Compose it again"))?
+        .message;
+
+    // The time may have passed while the preview was open
+    if let Some(scheduled_at) = scheduled.scheduled_at() {
+        check_not_past(scheduled_at)?;
+    }
+    scheduled.validate()?;
+
+    let _lock = lock_schedule(ctx).await;
+    let mut messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+    let id = ScheduledMessage::next_free_id(&messages, &completed);
+
+    scheduled.set_id(id);
+    messages.push(scheduled.clone());
+    ScheduledMessage::save_all_scheduled_messages(&messages)?;
+
+    info!(
+        "Scheduled message with id {id} was composed by {}",
+        component.user.name
+    );
+
+    let status = format!(
+        "Scheduled message with id {id} will be sent {} to {}",
+        display_time(&scheduled),
+        display_channel(&scheduled)
+    );
+
+    component
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                render(&scheduled, status).components(Vec::new()),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Discard the draft of the clicked preview
+pub async fn cancel(ctx: &Context, component: &ComponentInteraction) -> Result<(), Error> {
+    let draft_id = parse_draft_id(&component.data.custom_id)?;
+    get_drafts(ctx).await.lock().await.remove(&draft_id);
+
+    let response = CreateInteractionResponseMessage::new()
+        .content("The message was discarded")
+        .embeds(Vec::new())
+        .components(Vec::new());
+
+    component
+        .create_response(ctx, CreateInteractionResponse::UpdateMessage(response))
+        .await?;
+    Ok(())
+}
+
+/// The message as it will be sent, followed by the given status. No one is pinged by the preview
+fn render(scheduled: &ScheduledMessage, status: String) -> CreateInteractionResponseMessage {
+    let mut response =
+        CreateInteractionResponseMessage::new().allowed_mentions(CreateAllowedMentions::new());
+    let mut embeds = Vec::new();
+
+    if let Some(title) = &scheduled.embed_title {
+        response = response.content("");
+        embeds.push(
+            CreateEmbed::new()
+                .title(title)
+                .description(scheduled.message()),
+        );
+    } else {
+        response = response.content(scheduled.message());
+    }
+
+    embeds.push(CreateEmbed::new().description(status));
+    response.embeds(embeds)
+}
+
+async fn get_drafts(ctx: &Context) -> Arc<Mutex<HashMap<InteractionId, ComposeDraft>>> {
+    let data_read = ctx.data.read().await;
+    data_read.get::<ComposeDrafts>().unwrap().clone()
+}
+
+/// The id of the draft in the custom id of a preview button
+fn parse_draft_id(custom_id: &str) -> Result<InteractionId, Error> {
+    custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|id| id.parse::<InteractionId>().ok())
+        .ok_or_else(|| anyhow!("Invalid button id {custom_id}"))
+}
@@ -0,0 +1,394 @@
+pub mod compose;
+pub mod quiz;
+pub mod repost;
+pub mod schedule;
+
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serenity::builder::{
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::sync::Arc;
+use tokio::sync::OwnedMutexGuard;
+use tracing::{error, info};
+
+use crate::config::{AdminData, BotConfig};
+use crate::directory::{resolve_channel, resolve_guild, resolve_role};
+use crate::{BotAdmins, ScheduleLock};
+
+/// Register every slash command of the bot. Replaces any command that is no longer part of it.
+/// Without admins in the bot config, only members with the Manage Server permission can see the
+/// commands, which can be changed from the server settings. They are only allowed in the target
+/// guild
+pub async fn register_commands(ctx: &Context) {
+    let mut commands = vec![schedule::register(), quiz::register(), repost::register()];
+
+    if get_admins(ctx).await.is_none() {
+        commands = commands
+            .into_iter()
+            .map(|command| command.default_member_permissions(Permissions::MANAGE_GUILD))
+            .collect();
+    }
+
+    if let Err(e) = Command::set_global_commands(ctx, commands).await {
+        error!("Failed to register the slash commands. Reason: {e}");
+    } else {
+        info!("Slash commands registered");
+    }
+}
+
+/// Run the slash command that was used. Any failure is shown to the user who used it
+pub async fn run_command(ctx: &Context, command: &CommandInteraction) {
+    if !is_allowed(ctx, command).await {
+        info!(
+            "{} ({}) was denied the use of the /{} command",
+            command.user.name, command.user.id, command.data.name
+        );
+
+        if let Err(e) =
+            respond_ephemeral(ctx, command, "You are not allowed to use this command").await
+        {
+            error!(
+                "Failed to respond to the /{} command. Reason: {e}",
+                command.data.name
+            );
+        }
+        return;
+    }
+
+    let result = match command.data.name.as_str() {
+        "schedule" => schedule::run(ctx, command).await,
+        "quiz" => quiz::run(ctx, command).await,
+        repost::COMMAND_NAME => repost::run(ctx, command).await,
+        name => Err(anyhow!("Unknown command {name}")),
+    };
+
+    if let Err(e) = result {
+        error!(
+            "Failed to run the /{} command used by {}. Reason: {e}",
+            command.data.name, command.user.name
+        );
+
+        if let Err(e) = respond_ephemeral(ctx, command, format!("Failed: {e}")).await {
+            error!(
+                "Failed to respond to the /{} command. Reason: {e}",
+                command.data.name
+            );
+        }
+    }
+}
+
+/// Handle a submitted modal. The command that opened the modal was already allowed for the user.
+/// Any failure is shown to the user who submitted it
+pub async fn run_modal(ctx: &Context, modal: &ModalInteraction) {
+    let prefix = modal.data.custom_id.split(':').next().unwrap_or_default();
+
+    let result = match prefix {
+        repost::MODAL_ID => repost::submit(ctx, modal).await,
+        compose::MODAL_ID => compose::submit(ctx, modal).await,
+        _ => Err(anyhow!("Unknown modal {}", modal.data.custom_id)),
+    };
+
+    if let Err(e) = result {
+        error!(
+            "Failed to handle the {prefix} modal submitted by {}. Reason: {e}",
+            modal.user.name
+        );
+
+        // The response may have been deferred already
+        let response = CreateInteractionResponseMessage::new()
+            .content(format!("Failed: {e}"))
+            .ephemeral(true);
+
+        let result = if modal
+            .create_response(ctx, CreateInteractionResponse::Message(response))
+            .await
+            .is_err()
+        {
+            modal
+                .edit_response(
+                    ctx,
+                    EditInteractionResponse::new().content(format!("Failed: {e}")),
+                )
+                .await
+                .map(|_| ())
+        } else {
+            Ok(())
+        };
+
+        if let Err(e) = result {
+            error!("Failed to respond to the {prefix} modal. Reason: {e}");
+        }
+    }
+}
+
+/// Handle a clicked button. Only buttons on the responses of allowed commands exist. Any failure
+/// is shown to the user who clicked it
+pub async fn run_component(ctx: &Context, component: &ComponentInteraction) {
+    let prefix = component
+        .data
+        .custom_id
+        .split(':')
+        .next()
+        .unwrap_or_default();
+
+    let result = match prefix {
+        compose::CONFIRM_ID => compose::confirm(ctx, component).await,
+        compose::CANCEL_ID => compose::cancel(ctx, component).await,
+        _ => Err(anyhow!("Unknown button {}", component.data.custom_id)),
+    };
+
+    if let Err(e) = result {
+        error!(
+            "Failed to handle the {prefix} button clicked by {}. Reason: {e}",
+            component.user.name
+        );
+
+        let response = CreateInteractionResponseMessage::new()
+            .content(format!("Failed: {e}"))
+            .ephemeral(true);
+
+        if let Err(e) = component
+            .create_response(ctx, CreateInteractionResponse::Message(response))
+            .await
+        {
+            error!("Failed to respond to the {prefix} button. Reason: {e}");
+        }
+    }
+}
+
+/// The value of a text input in a submitted modal
+pub fn modal_value(modal: &ModalInteraction, custom_id: &str) -> Option<String> {
+    modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == custom_id => {
+                input.value.clone()
+            }
+            _ => None,
+        })
+}
+
+/// Find a channel given in a modal by its mention, id or name
+pub async fn find_channel_input(
+    ctx: &Context,
+    guild_id: GuildId,
+    input: &str,
+) -> Result<GuildChannel, Error> {
+    let input = input.trim();
+    let id = input
+        .trim_start_matches("<#")
+        .trim_end_matches('>')
+        .parse::<ChannelId>()
+        .ok();
+
+    let name = if id.is_none() {
+        Some(input.trim_start_matches('#'))
+    } else {
+        None
+    };
+
+    resolve_channel(ctx, guild_id, id, name, false)
+        .await?
+        .ok_or_else(|| anyhow!("Channel {input} was not found"))
+}
+
+/// Suggest values for the option that is being typed in a slash command
+pub async fn run_autocomplete(ctx: &Context, autocomplete: &CommandInteraction) {
+    if !is_allowed(ctx, autocomplete).await {
+        return;
+    }
+
+    let result = match autocomplete.data.name.as_str() {
+        "schedule" => schedule::autocomplete(ctx, autocomplete).await,
+        "quiz" => quiz::autocomplete(ctx, autocomplete).await,
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        error!(
+            "Failed to suggest values for the /{} command. Reason: {e}",
+            autocomplete.data.name
+        );
+    }
+}
+
+/// Get the admins from the bot config
+async fn get_admins(ctx: &Context) -> Arc<Option<Vec<AdminData>>> {
+    let data_read = ctx.data.read().await;
+    data_read.get::<BotAdmins>().unwrap().clone()
+}
+
+/// The scope in the admins of the bot config that allows the command
+fn command_scope(command_name: &str) -> &str {
+    match command_name {
+        repost::COMMAND_NAME => "schedule",
+        name => name,
+    }
+}
+
+/// The target guild of the bot config
+async fn get_target_guild(ctx: &Context) -> Option<GuildId> {
+    let config = BotConfig::get_config();
+
+    if let Err(e) = &config {
+        error!("Failed to read the bot config. Reason: {e}");
+        return None;
+    }
+
+    let config = config.unwrap();
+
+    match resolve_guild(
+        ctx,
+        config.get_target_guild_id(),
+        config.get_target_guild().as_deref(),
+    )
+    .await
+    {
+        Ok(guild_id) => guild_id,
+        Err(e) => {
+            error!("Failed to fetch the guild list. Reason: {e}");
+            None
+        }
+    }
+}
+
+/// The target channel of the bot config, where scheduled messages without a channel are sent
+pub async fn get_target_channel(ctx: &Context) -> Option<ChannelId> {
+    let guild_id = get_target_guild(ctx).await?;
+    let config = BotConfig::get_config().ok()?;
+
+    match resolve_channel(
+        ctx,
+        guild_id,
+        config.get_target_channel_id(),
+        config.get_target_channel().as_deref(),
+        config.search_archived_threads(),
+    )
+    .await
+    {
+        Ok(channel) => channel.map(|channel| channel.id),
+        Err(e) => {
+            error!("Failed to find the target channel. Reason: {e}");
+            None
+        }
+    }
+}
+
+/// Whether the user of the command is one of the admins allowed to use it, directly or by one of
+/// their roles. Roles are given by their id or by their name. Everyone is allowed without admins
+/// in the bot config as the command permissions in discord are used instead. The commands can only
+/// be used in the target guild either way
+async fn is_allowed(ctx: &Context, command: &CommandInteraction) -> bool {
+    // The commands are global, so the Manage Server permission would otherwise be checked in
+    // whichever guild they are used in
+    let guild_id = match get_target_guild(ctx).await {
+        Some(guild_id) if command.guild_id == Some(guild_id) => guild_id,
+        _ => return false,
+    };
+
+    let admins = get_admins(ctx).await;
+
+    let admins = if let Some(admins) = admins.as_ref() {
+        admins
+    } else {
+        return true;
+    };
+
+    let scope = command_scope(&command.data.name);
+    let member_roles = command
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+
+    for admin in admins.iter().filter(|admin| admin.allows_scope(scope)) {
+        if admin.has_user(command.user.id) {
+            return true;
+        }
+
+        for role in admin.roles() {
+            let role_id = if let Ok(id) = role.parse::<RoleId>() {
+                Some(id)
+            } else {
+                match resolve_role(ctx, guild_id, &role).await {
+                    Ok(role_id) => role_id,
+                    Err(e) => {
+                        error!("Failed to fetch the roles of the guild {guild_id}. Reason: {e}");
+                        None
+                    }
+                }
+            };
+
+            if role_id.is_some_and(|id| member_roles.contains(&id)) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Lock schedule.json until the returned guard is dropped. Must be held from reading schedule.json
+/// until the changed messages are saved
+pub async fn lock_schedule(ctx: &Context) -> OwnedMutexGuard<()> {
+    let lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<ScheduleLock>().unwrap().clone()
+    };
+
+    lock.lock_owned().await
+}
+
+/// Respond to the command with a message only the user who used it can see
+pub async fn respond_ephemeral(
+    ctx: &Context,
+    command: &CommandInteraction,
+    content: impl Into<String>,
+) -> Result<(), Error> {
+    let response = CreateInteractionResponseMessage::new()
+        .content(content)
+        .ephemeral(true);
+
+    command
+        .create_response(ctx, CreateInteractionResponse::Message(response))
+        .await?;
+    Ok(())
+}
+
+/// Parse a time given in a command in UTC. Accepts the format of schedule.json along with a
+/// shorter form without seconds such as `2024-06-01 12:00`. The time must not have passed
+pub fn parse_time(time: &str) -> Result<DateTime<Utc>, Error> {
+    let time = time.trim();
+
+    let parsed = if let Ok(parsed) = time.parse::<DateTime<Utc>>() {
+        parsed
+    } else {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M"))
+            .map_err(|_| {
+                anyhow!("{time} is not a valid time. Use the YYYY-MM-DD HH:MM format in UTC")
+            })?
+            .and_utc()
+    };
+
+    check_not_past(parsed)?;
+    Ok(parsed)
+}
+
+/// Fail if the time to send a message at has already passed
+pub fn check_not_past(time: DateTime<Utc>) -> Result<(), Error> {
+    if time < Utc::now() {
+        return Err(anyhow!(
+            "{} UTC is in the past",
+            time.format("%Y-%m-%d %H:%M")
+        ));
+    }
+
+    Ok(())
+}
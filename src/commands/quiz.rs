@@ -0,0 +1,288 @@
+use anyhow::{anyhow, Error};
+use chrono::Utc;
+use serenity::builder::{
+    CreateAllowedMentions, CreateAutocompleteResponse, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateMessage,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use tracing::info;
+
+use super::respond_ephemeral;
+use crate::config::{BotConfig, QuizData};
+use crate::utils::{
+    create_allowed_mentions, get_ongoing_quiz, minutes_later, remove_ongoing_quiz, set_ongoing_quiz,
+};
+
+const DEFAULT_REPLY: &str = "Congratulations! You've got the right answer!";
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+const MAX_MESSAGE_LENGTH: u16 = 2000;
+const MAX_END_AFTER_MINUTES: u64 = 525_600;
+
+pub fn register() -> CreateCommand {
+    let start = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "start",
+        "Start a quiz from quiz.json or a one-off quiz right away",
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "quiz_id",
+            "The id of the quiz in quiz.json to start",
+        )
+        .min_int_value(0)
+        .set_autocomplete(true),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "question",
+            "The question to post in the channel. Required for a one-off quiz",
+        )
+        .max_length(MAX_MESSAGE_LENGTH),
+    )
+    .add_sub_option(CreateCommandOption::new(
+        CommandOptionType::String,
+        "answer",
+        "The answer of a one-off quiz",
+    ))
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "reply_with",
+            "The reply to the winner of a one-off quiz",
+        )
+        .max_length(MAX_MESSAGE_LENGTH),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "end_after",
+            "Minutes after which a one-off quiz ends",
+        )
+        .min_int_value(1)
+        .max_int_value(MAX_END_AFTER_MINUTES),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "channel",
+            "The channel of the quiz. Defaults to the current channel",
+        )
+        .channel_types(vec![
+            ChannelType::Text,
+            ChannelType::News,
+            ChannelType::PublicThread,
+            ChannelType::PrivateThread,
+            ChannelType::NewsThread,
+        ]),
+    );
+
+    let stop = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "stop",
+        "End the ongoing quiz",
+    )
+    .add_sub_option(CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "reveal",
+        "Whether to post the answer in the quiz channel",
+    ));
+
+    let status = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "status",
+        "Show whether a quiz is ongoing",
+    );
+
+    CreateCommand::new("quiz")
+        .description("Manage quizzes")
+        .dm_permission(false)
+        .add_option(start)
+        .add_option(stop)
+        .add_option(status)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or_else(|| anyhow!("No subcommand was given"))?;
+
+    match (subcommand.name, &subcommand.value) {
+        ("start", ResolvedValue::SubCommand(options)) => start(ctx, command, options).await,
+        ("stop", ResolvedValue::SubCommand(options)) => stop(ctx, command, options).await,
+        ("status", ResolvedValue::SubCommand(_)) => status(ctx, command).await,
+        (name, _) => Err(anyhow!("Unknown subcommand {name}")),
+    }
+}
+
+/// Suggest the ids of the quizzes in quiz.json, matching what was typed so far
+pub async fn autocomplete(ctx: &Context, autocomplete: &CommandInteraction) -> Result<(), Error> {
+    let typed = autocomplete
+        .data
+        .autocomplete()
+        .map(|option| option.value.to_string())
+        .unwrap_or_default();
+
+    let quizzes = QuizData::get_all_quiz_data()?;
+    let mut response = CreateAutocompleteResponse::new();
+
+    for quiz in quizzes
+        .iter()
+        .filter(|quiz| quiz.id().to_string().starts_with(&typed))
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+    {
+        response = response.add_int_choice(quiz.id().to_string(), quiz.id() as i64);
+    }
+
+    autocomplete
+        .create_response(ctx, CreateInteractionResponse::Autocomplete(response))
+        .await?;
+    Ok(())
+}
+
+/// Start monitoring a channel for the answer of a quiz right away. Replaces any ongoing quiz
+async fn start(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let mut quiz_id = None;
+    let mut question = None;
+    let mut answer = None;
+    let mut reply_with = None;
+    let mut end_after = None;
+    let mut channel_id = command.channel_id;
+
+    for option in options {
+        match (option.name, &option.value) {
+            ("quiz_id", ResolvedValue::Integer(value)) => quiz_id = Some(*value as u32),
+            ("question", ResolvedValue::String(value)) => question = Some(value.to_string()),
+            ("answer", ResolvedValue::String(value)) => answer = Some(value.to_string()),
+            ("reply_with", ResolvedValue::String(value)) => reply_with = Some(value.to_string()),
+            ("end_after", ResolvedValue::Integer(value)) => end_after = Some(*value),
+            ("channel", ResolvedValue::Channel(channel)) => channel_id = channel.id,
+            _ => {}
+        }
+    }
+
+    let mut quiz = if let Some(id) = quiz_id {
+        if answer.is_some() || reply_with.is_some() || end_after.is_some() {
+            return Err(anyhow!(
+                "answer, reply_with and end_after cannot be used along with quiz_id"
+            ));
+        }
+
+        QuizData::get_quiz_data(id)?
+    } else {
+        let answer = answer.ok_or_else(|| anyhow!("Either quiz_id or answer is required"))?;
+
+        if question.is_none() {
+            return Err(anyhow!("question is required for a one-off quiz"));
+        }
+
+        let end_at = match end_after {
+            Some(minutes) => Some(
+                u64::try_from(minutes)
+                    .ok()
+                    .filter(|minutes| (1..=MAX_END_AFTER_MINUTES).contains(minutes))
+                    .and_then(|minutes| minutes_later(Utc::now(), minutes))
+                    .ok_or_else(|| {
+                        anyhow!("end_after must be between 1 and {MAX_END_AFTER_MINUTES} minutes")
+                    })?,
+            ),
+            None => None,
+        };
+
+        QuizData::new_one_off(
+            answer,
+            reply_with.unwrap_or_else(|| DEFAULT_REPLY.to_string()),
+            end_at,
+        )
+    };
+
+    quiz.set_monitor_channel_id(channel_id);
+
+    if let Some(question) = question {
+        let message = CreateMessage::new()
+            .content(question)
+            .allowed_mentions(quiz_mentions(ctx, command).await?);
+
+        channel_id.send_message(ctx, message).await?;
+    }
+
+    set_ongoing_quiz(ctx, quiz).await;
+
+    info!(
+        "A quiz was started in {channel_id} by {}",
+        command.user.name
+    );
+
+    respond_ephemeral(ctx, command, format!("Quiz started in <#{channel_id}>")).await
+}
+
+/// End the ongoing quiz, optionally posting the answer in the quiz channel
+async fn stop(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let reveal = options.iter().any(|option| {
+        option.name == "reveal" && matches!(option.value, ResolvedValue::Boolean(true))
+    });
+
+    let quiz = get_ongoing_quiz(ctx)
+        .await
+        .ok_or_else(|| anyhow!("No quiz is ongoing"))?;
+
+    remove_ongoing_quiz(ctx).await;
+
+    info!("The ongoing quiz was stopped by {}", command.user.name);
+
+    if reveal {
+        let reveal_message = CreateMessage::new()
+            .content(format!(
+                "The quiz has ended. The answer was: {}",
+                quiz.answer()
+            ))
+            .allowed_mentions(quiz_mentions(ctx, command).await?);
+
+        quiz.get_monitor_channel_id()
+            .send_message(ctx, reveal_message)
+            .await?;
+    }
+
+    respond_ephemeral(ctx, command, "The quiz was stopped").await
+}
+
+/// The allowed mentions of the bot config for the messages posted in the quiz channel
+async fn quiz_mentions(
+    ctx: &Context,
+    command: &CommandInteraction,
+) -> Result<CreateAllowedMentions, Error> {
+    let guild_id = command
+        .guild_id
+        .ok_or_else(|| anyhow!("Quizzes can only be started in a guild"))?;
+    let config = BotConfig::get_config()?;
+
+    create_allowed_mentions(ctx, guild_id, &config.allowed_mentions()).await
+}
+
+/// Show where the ongoing quiz is monitored and when it ends. The answer is not shown
+async fn status(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let content = if let Some(quiz) = get_ongoing_quiz(ctx).await {
+        let mut content = format!("A quiz is ongoing in <#{}>", quiz.get_monitor_channel_id());
+
+        if let Some(end_at) = quiz.end_at() {
+            content.push_str(&format!(" and ends <t:{}:R>", end_at.timestamp()));
+        }
+
+        content
+    } else {
+        "No quiz is ongoing".to_string()
+    };
+
+    respond_ephemeral(ctx, command, content).await
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Error};
+use serenity::builder::{
+    CreateActionRow, CreateCommand, CreateInputText, CreateInteractionResponse, CreateModal,
+    EditInteractionResponse,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+use super::{find_channel_input, lock_schedule, modal_value, parse_time};
+use crate::config::{CompletedScheduled, ScheduledMessage};
+
+pub const COMMAND_NAME: &str = "Schedule repost…";
+pub const MODAL_ID: &str = "schedule_repost";
+const REPOST_FOLDER: &str = "files/reposts";
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new(COMMAND_NAME)
+        .kind(CommandType::Message)
+        .dm_permission(false)
+}
+
+/// Open the modal asking when and where to repost the message. The message is found again by its
+/// id once the modal is submitted
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let message_id = command
+        .data
+        .target_id
+        .ok_or_else(|| anyhow!("No message was given"))?
+        .to_message_id();
+
+    let time = CreateInputText::new(InputTextStyle::Short, "Time in UTC", "time")
+        .placeholder("2024-06-01 12:00")
+        .required(true);
+
+    let channel = CreateInputText::new(InputTextStyle::Short, "Channel", "channel")
+        .placeholder("Name, ID or mention. Defaults to the channel of the message")
+        .required(false);
+
+    let modal = CreateModal::new(
+        format!("{MODAL_ID}:{}:{message_id}", command.channel_id),
+        "Schedule repost",
+    )
+    .components(vec![
+        CreateActionRow::InputText(time),
+        CreateActionRow::InputText(channel),
+    ]);
+
+    command
+        .create_response(ctx, CreateInteractionResponse::Modal(modal))
+        .await?;
+    Ok(())
+}
+
+/// Create a scheduled message with the content and attachments of the message from the submitted
+/// modal. Attachments are downloaded into a folder of the new scheduled message
+pub async fn submit(ctx: &Context, modal: &ModalInteraction) -> Result<(), Error> {
+    let (channel_id, message_id) = parse_modal_id(&modal.data.custom_id)?;
+    let guild_id = modal
+        .guild_id
+        .ok_or_else(|| anyhow!("Messages can only be reposted in a guild"))?;
+
+    let scheduled_at = parse_time(&modal_value(modal, "time").unwrap_or_default())?;
+
+    let target_channel = match modal_value(modal, "channel") {
+        Some(input) if !input.trim().is_empty() => {
+            let channel = find_channel_input(ctx, guild_id, &input).await?;
+
+            if channel.kind == ChannelType::Forum {
+                return Err(anyhow!(
+                    "Forum channels need a forum_title which cannot be set here"
+                ));
+            }
+
+            channel.id
+        }
+        _ => channel_id,
+    };
+
+    // Downloading the attachments can take longer than discord waits for a response
+    modal.defer_ephemeral(ctx).await?;
+
+    let message = channel_id.message(ctx, message_id).await?;
+
+    if message.content.is_empty() && message.attachments.is_empty() {
+        return Err(anyhow!(
+            "The message has no content or attachments to repost"
+        ));
+    }
+
+    // The id is only given once everything is downloaded so schedule.json is not locked meanwhile
+    let mut scheduled = ScheduledMessage::new(0, message.content.clone(), scheduled_at);
+    scheduled.target_guild_id = Some(guild_id);
+    scheduled.target_channel_id = Some(target_channel);
+    scheduled.validate()?;
+
+    let mut downloads = Vec::new();
+
+    for (index, attachment) in message.attachments.iter().enumerate() {
+        let data = attachment.download().await?;
+
+        // Keep only the file name so the attachment cannot be written outside the folder. The
+        // index keeps attachments with the same name apart
+        let file_name = Path::new(&attachment.filename)
+            .file_name()
+            .ok_or_else(|| anyhow!("Attachment {} has no file name", attachment.filename))?
+            .to_string_lossy()
+            .to_string();
+
+        downloads.push((format!("{index}_{file_name}"), data));
+    }
+
+    let lock = lock_schedule(ctx).await;
+    let mut messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+    let id = ScheduledMessage::next_free_id(&messages, &completed);
+
+    scheduled.set_id(id);
+
+    if !downloads.is_empty() {
+        let folder = format!("{REPOST_FOLDER}/{id}");
+        fs::create_dir_all(&folder)?;
+
+        let mut attachments = Vec::new();
+
+        for (file_name, data) in downloads {
+            let location = format!("{folder}/{file_name}");
+            fs::write(&location, data)?;
+            attachments.push(location);
+        }
+
+        scheduled.attachments = Some(attachments);
+    }
+
+    let response = super::schedule::preview(&scheduled);
+
+    messages.push(scheduled);
+    ScheduledMessage::save_all_scheduled_messages(&messages)?;
+    drop(lock);
+
+    info!(
+        "Scheduled message with id {id} was created as a repost of message {message_id} by {}",
+        modal.user.name
+    );
+
+    modal
+        .edit_response(ctx, EditInteractionResponse::new().content(response))
+        .await?;
+    Ok(())
+}
+
+/// The channel and message ids in the custom id of the modal
+fn parse_modal_id(custom_id: &str) -> Result<(ChannelId, MessageId), Error> {
+    let mut parts = custom_id.split(':').skip(1);
+
+    let channel_id = parts
+        .next()
+        .and_then(|id| id.parse::<ChannelId>().ok())
+        .ok_or_else(|| anyhow!("Invalid modal id {custom_id}"))?;
+
+    let message_id = parts
+        .next()
+        .and_then(|id| id.parse::<MessageId>().ok())
+        .ok_or_else(|| anyhow!("Invalid modal id {custom_id}"))?;
+
+    Ok((channel_id, message_id))
+}
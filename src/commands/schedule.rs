@@ -0,0 +1,496 @@
+use anyhow::{anyhow, Error};
+use serenity::builder::{
+    CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use tracing::info;
+
+use super::{compose, get_target_channel, lock_schedule, parse_time, respond_ephemeral};
+use crate::config::{CompletedScheduled, QuizData, ScheduledMessage};
+use crate::utils::create_poll;
+
+const MAX_MESSAGE_LENGTH: u16 = 2000;
+const LIST_PAGE_SIZE: usize = 10;
+const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+const MAX_RESPONSE_LENGTH: usize = 2000;
+const PREVIEW_LENGTH: usize = 50;
+
+pub fn register() -> CreateCommand {
+    let create = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "create",
+        "Schedule a new message",
+    )
+    .add_sub_option(
+        CreateCommandOption::new(CommandOptionType::String, "message", "The message to send")
+            .required(true)
+            .max_length(MAX_MESSAGE_LENGTH),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "time",
+            "When to send the message in UTC, such as 2024-06-01 12:00",
+        )
+        .required(true),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Channel,
+            "channel",
+            "The channel to send the message to. Defaults to the target channel of the bot",
+        )
+        .channel_types(vec![
+            ChannelType::Text,
+            ChannelType::News,
+            ChannelType::PublicThread,
+            ChannelType::PrivateThread,
+            ChannelType::NewsThread,
+        ]),
+    )
+    .add_sub_option(CreateCommandOption::new(
+        CommandOptionType::Boolean,
+        "pin",
+        "Whether to pin the message",
+    ))
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "poll_id",
+            "The id of the poll in poll.json to attach",
+        )
+        .min_int_value(0),
+    )
+    .add_sub_option(
+        CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "quiz_id",
+            "The id of the quiz in quiz.json to start",
+        )
+        .min_int_value(0),
+    );
+
+    let list = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "list",
+        "List the scheduled messages that are not sent yet",
+    )
+    .add_sub_option(CreateCommandOption::new(
+        CommandOptionType::Channel,
+        "channel",
+        "Only list the messages sent to this channel",
+    ))
+    .add_sub_option(
+        CreateCommandOption::new(CommandOptionType::Integer, "page", "The page to show")
+            .min_int_value(1),
+    );
+
+    let show = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "show",
+        "Show the full definition of a scheduled message",
+    )
+    .add_sub_option(id_option());
+
+    let cancel = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "cancel",
+        "Cancel a scheduled message so it is never sent",
+    )
+    .add_sub_option(id_option());
+
+    let compose = CreateCommandOption::new(
+        CommandOptionType::SubCommand,
+        "compose",
+        "Write a long scheduled message in a form",
+    );
+
+    CreateCommand::new("schedule")
+        .description("Manage scheduled messages")
+        .dm_permission(false)
+        .add_option(create)
+        .add_option(compose)
+        .add_option(list)
+        .add_option(show)
+        .add_option(cancel)
+}
+
+fn id_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Integer,
+        "id",
+        "The id of the scheduled message",
+    )
+    .required(true)
+    .min_int_value(0)
+    .set_autocomplete(true)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Result<(), Error> {
+    let options = command.data.options();
+    let subcommand = options
+        .first()
+        .ok_or_else(|| anyhow!("No subcommand was given"))?;
+
+    match (subcommand.name, &subcommand.value) {
+        ("create", ResolvedValue::SubCommand(options)) => create(ctx, command, options).await,
+        ("compose", ResolvedValue::SubCommand(_)) => compose::open(ctx, command).await,
+        ("list", ResolvedValue::SubCommand(options)) => list(ctx, command, options).await,
+        ("show", ResolvedValue::SubCommand(options)) => show(ctx, command, options).await,
+        ("cancel", ResolvedValue::SubCommand(options)) => cancel(ctx, command, options).await,
+        (name, _) => Err(anyhow!("Unknown subcommand {name}")),
+    }
+}
+
+/// Add a new scheduled message to schedule.json with the next free id
+async fn create(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let mut message = None;
+    let mut time = None;
+    let mut channel_id = None;
+    let mut pin = None;
+    let mut poll_id = None;
+    let mut quiz_id = None;
+
+    for option in options {
+        match (option.name, &option.value) {
+            ("message", ResolvedValue::String(value)) => message = Some(value.to_string()),
+            ("time", ResolvedValue::String(value)) => time = Some(*value),
+            ("channel", ResolvedValue::Channel(channel)) => channel_id = Some(channel.id),
+            ("pin", ResolvedValue::Boolean(value)) => pin = Some(*value),
+            ("poll_id", ResolvedValue::Integer(value)) => poll_id = Some(*value as u32),
+            ("quiz_id", ResolvedValue::Integer(value)) => quiz_id = Some(*value as u32),
+            _ => {}
+        }
+    }
+
+    let message = message.ok_or_else(|| anyhow!("message is required"))?;
+    let scheduled_at = parse_time(time.ok_or_else(|| anyhow!("time is required"))?)?;
+
+    // Check the poll and quiz now so a wrong id does not fail only once it is time to send
+    if let Some(id) = poll_id {
+        let _ = create_poll(id)?;
+    }
+
+    if let Some(id) = quiz_id {
+        QuizData::get_quiz_data(id)?;
+    }
+
+    let _lock = lock_schedule(ctx).await;
+    let mut messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+    let id = ScheduledMessage::next_free_id(&messages, &completed);
+
+    let mut scheduled = ScheduledMessage::new(id, message, scheduled_at);
+
+    if channel_id.is_some() {
+        scheduled.target_guild_id = command.guild_id;
+        scheduled.target_channel_id = channel_id;
+    }
+
+    scheduled.to_pin = pin;
+    scheduled.poll_id = poll_id;
+    scheduled.quiz_id = quiz_id;
+    scheduled.validate()?;
+    let response = preview(&scheduled);
+
+    messages.push(scheduled);
+    ScheduledMessage::save_all_scheduled_messages(&messages)?;
+
+    info!(
+        "Scheduled message with id {id} was created by {}",
+        command.user.name
+    );
+
+    respond_ephemeral(ctx, command, response).await
+}
+
+/// Suggest the ids of the scheduled messages that are not sent yet, matching what was typed so far
+pub async fn autocomplete(ctx: &Context, autocomplete: &CommandInteraction) -> Result<(), Error> {
+    let typed = autocomplete
+        .data
+        .autocomplete()
+        .map(|option| option.value.to_string())
+        .unwrap_or_default();
+
+    let messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+
+    let mut response = CreateAutocompleteResponse::new();
+
+    for message in messages
+        .iter()
+        .filter(|message| !completed.completed.contains(&message.id()) && !message.is_disabled())
+        .filter(|message| message.id().to_string().starts_with(&typed))
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+    {
+        response = response.add_int_choice(
+            format!("{}: {}", message.id(), shorten(&message.message())),
+            message.id() as i64,
+        );
+    }
+
+    autocomplete
+        .create_response(ctx, CreateInteractionResponse::Autocomplete(response))
+        .await?;
+    Ok(())
+}
+
+/// List the scheduled messages that are not completed or cancelled, the earliest first
+async fn list(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let mut channel = None;
+    let mut page = 1;
+
+    for option in options {
+        match (option.name, &option.value) {
+            ("channel", ResolvedValue::Channel(value)) => channel = Some(*value),
+            ("page", ResolvedValue::Integer(value)) => page = *value as usize,
+            _ => {}
+        }
+    }
+
+    let messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+
+    // Messages without a channel are sent to the target channel of the bot config
+    let is_target_channel = if let Some(channel) = channel {
+        get_target_channel(ctx).await == Some(channel.id)
+    } else {
+        false
+    };
+
+    let mut upcoming = messages
+        .iter()
+        .filter(|message| !completed.completed.contains(&message.id()) && !message.is_disabled())
+        .filter(|message| {
+            channel.is_none_or(|channel| {
+                message.sends_to_channel(channel.id, channel.name.as_deref(), is_target_channel)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Messages relative to an event have no fixed time and are listed last
+    upcoming.sort_by_key(|message| (message.scheduled_at().is_none(), message.scheduled_at()));
+
+    if upcoming.is_empty() {
+        return respond_ephemeral(ctx, command, "No scheduled messages are waiting to be sent")
+            .await;
+    }
+
+    let total_pages = upcoming.len().div_ceil(LIST_PAGE_SIZE);
+    let page = page.min(total_pages);
+
+    let lines = upcoming
+        .iter()
+        .skip((page - 1) * LIST_PAGE_SIZE)
+        .take(LIST_PAGE_SIZE)
+        .map(|message| {
+            format!(
+                "`{}` {} to {}: {}",
+                message.id(),
+                display_time(message),
+                display_channel(message),
+                shorten(&message.message())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let content = format!(
+        "{lines}\n\nPage {page} of {total_pages}, {} scheduled messages",
+        upcoming.len()
+    );
+
+    respond_ephemeral(ctx, command, content).await
+}
+
+/// Show the full definition of a scheduled message as it is in schedule.json along with whether
+/// it was sent
+async fn show(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let id = get_id(options)?;
+    let messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+
+    let message = messages
+        .iter()
+        .find(|message| message.id() == id)
+        .ok_or_else(|| anyhow!("Scheduled message with id {id} was not found"))?;
+
+    let state = if completed.completed.contains(&id) {
+        "Sent".to_string()
+    } else if message.is_disabled() {
+        "Cancelled".to_string()
+    } else if let Some(targets) = completed.completed_targets.get(&id) {
+        format!(
+            "Waiting, sent to {} of {} targets",
+            targets.len(),
+            message.get_targets().len()
+        )
+    } else {
+        "Waiting".to_string()
+    };
+
+    let definition = serde_json::to_string_pretty(&message.redacted())?;
+    let header = format!("State: {state}\n");
+
+    // Leave room for the header and the code block
+    let max_length = MAX_RESPONSE_LENGTH - header.len() - 12;
+    let definition = if definition.chars().count() > max_length {
+        let mut shortened = definition.chars().take(max_length - 3).collect::<String>();
+        shortened.push_str("...");
+        shortened
+    } else {
+        definition
+    };
+
+    respond_ephemeral(ctx, command, format!("{header}```json\n{definition}\n```")).await
+}
+
+/// Cancel a scheduled message by disabling it in schedule.json
+async fn cancel(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[ResolvedOption<'_>],
+) -> Result<(), Error> {
+    let id = get_id(options)?;
+    let _lock = lock_schedule(ctx).await;
+    let mut messages = ScheduledMessage::get_all_scheduled_messages()?;
+    let completed = CompletedScheduled::get_completed_scheduled()?;
+
+    if completed.completed.contains(&id) {
+        return Err(anyhow!("Scheduled message with id {id} was already sent"));
+    }
+
+    let message = messages
+        .iter_mut()
+        .find(|message| message.id() == id)
+        .ok_or_else(|| anyhow!("Scheduled message with id {id} was not found"))?;
+
+    if message.is_disabled() {
+        return Err(anyhow!(
+            "Scheduled message with id {id} is already cancelled"
+        ));
+    }
+
+    message.disabled = Some(true);
+    ScheduledMessage::save_all_scheduled_messages(&messages)?;
+
+    info!(
+        "Scheduled message with id {id} was cancelled by {}",
+        command.user.name
+    );
+
+    respond_ephemeral(
+        ctx,
+        command,
+        format!("Scheduled message with id {id} was cancelled"),
+    )
+    .await
+}
+
+fn get_id(options: &[ResolvedOption<'_>]) -> Result<u32, Error> {
+    options
+        .iter()
+        .find_map(|option| match (option.name, &option.value) {
+            ("id", ResolvedValue::Integer(value)) => Some(*value as u32),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("id is required"))
+}
+
+/// The first line of the message, cut short if it is too long
+fn shorten(message: &str) -> String {
+    let first_line = message.lines().next().unwrap_or_default();
+
+    if first_line.chars().count() > PREVIEW_LENGTH {
+        let mut shortened = first_line.chars().take(PREVIEW_LENGTH).collect::<String>();
+        shortened.push_str("...");
+        shortened
+    } else {
+        first_line.to_string()
+    }
+}
+
+pub fn display_time(scheduled: &ScheduledMessage) -> String {
+    scheduled
+        .scheduled_at()
+        .map(|time| format!("<t:{}:f>", time.timestamp()))
+        .unwrap_or_else(|| "relative to an event".to_string())
+}
+
+pub fn display_channel(scheduled: &ScheduledMessage) -> String {
+    if scheduled.is_direct_message() {
+        return "direct messages".to_string();
+    }
+
+    let targets = scheduled.get_targets();
+
+    if targets.is_empty() {
+        return "the target channel".to_string();
+    }
+
+    targets
+        .iter()
+        .map(|target| {
+            target
+                .channel_id
+                .map(|id| format!("<#{id}>"))
+                .unwrap_or_else(|| target.channel_name())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A short summary of the scheduled message along with its content
+pub fn preview(scheduled: &ScheduledMessage) -> String {
+    let time = display_time(scheduled);
+    let channel = display_channel(scheduled);
+
+    let mut preview = format!(
+        "Scheduled message with id {} will be sent {time} to {channel}",
+        scheduled.id()
+    );
+
+    if scheduled.to_pin == Some(true) {
+        preview.push_str("\nIt will be pinned");
+    }
+
+    if let Some(id) = scheduled.poll_id {
+        preview.push_str(&format!("\nPoll: {id}"));
+    }
+
+    if let Some(id) = scheduled.quiz_id {
+        preview.push_str(&format!("\nQuiz: {id}"));
+    }
+
+    let quoted = scheduled
+        .message()
+        .lines()
+        .map(|line| format!("> {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // The quote adds to the length of the message so shorten it to fit in a response
+    let max_length = MAX_RESPONSE_LENGTH - preview.chars().count() - 2;
+    let quoted = if quoted.chars().count() > max_length {
+        let mut shortened = quoted.chars().take(max_length - 3).collect::<String>();
+        shortened.push_str("...");
+        shortened
+    } else {
+        quoted
+    };
+
+    format!("{preview}\n\n{quoted}")
+}
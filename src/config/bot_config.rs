@@ -1,28 +1,74 @@
-use anyhow::Context;
 use serde::Deserialize;
-use std::error::Error;
-use std::fs::File;
-use std::io::Read;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+
+use super::{parse_config, read_config, AllowedMentionsData, ConfigError};
+
+const MAX_PINS: usize = 50;
 
 #[derive(Deserialize)]
 pub struct BotConfig {
     bot_token: String,
-    target_guild: String,
-    target_channel: String,
+    target_guild: Option<String>,
+    target_guild_id: Option<GuildId>,
+    target_channel: Option<String>,
+    target_channel_id: Option<ChannelId>,
     pin_all: Option<bool>,
+    unpin_oldest_at: Option<usize>,
+    crosspost_all: Option<bool>,
+    search_archived_threads: Option<bool>,
+    allowed_mentions: Option<AllowedMentionsData>,
+    silent_all: Option<bool>,
+    admins: Option<Vec<AdminData>>,
+}
+
+/// Roles and users that are allowed to use the commands of the bot. Without scopes, every command
+/// is allowed
+#[derive(Deserialize, Clone)]
+pub struct AdminData {
+    roles: Option<Vec<String>>,
+    users: Option<Vec<UserId>>,
+    scopes: Option<Vec<String>>,
+}
+
+impl AdminData {
+    /// The role names or ids
+    pub fn roles(&self) -> Vec<String> {
+        self.roles.clone().unwrap_or_default()
+    }
+
+    pub fn has_user(&self, user_id: UserId) -> bool {
+        self.users
+            .as_ref()
+            .is_some_and(|users| users.contains(&user_id))
+    }
+
+    /// Whether the command with the given scope is allowed
+    pub fn allows_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.iter().any(|allowed| allowed == scope))
+    }
 }
 
 impl BotConfig {
-    pub fn get_config() -> Result<Self, Box<dyn Error>> {
-        let mut file =
-            File::open("config/bot_config.json").context("Failed to open bot_config.json")?;
-        let mut json_string = String::new();
+    pub fn get_config() -> Result<Self, ConfigError> {
+        let json_string = read_config("bot_config.json")?;
+        let result: BotConfig = parse_config("bot_config.json", &json_string)?;
 
-        file.read_to_string(&mut json_string)
-            .context("Failed to read bot_config.json")?;
+        if result.target_guild.is_none() && result.target_guild_id.is_none() {
+            return Err(ConfigError::Invalid {
+                file: "bot_config.json".to_string(),
+                message: "Either target_guild or target_guild_id must be provided".to_string(),
+            });
+        }
+
+        if result.target_channel.is_none() && result.target_channel_id.is_none() {
+            return Err(ConfigError::Invalid {
+                file: "bot_config.json".to_string(),
+                message: "Either target_channel or target_channel_id must be provided".to_string(),
+            });
+        }
 
-        let result: BotConfig =
-            serde_json::from_str(&json_string).context("Failed to parse bot_config.json")?;
         Ok(result)
     }
 
@@ -30,15 +76,56 @@ impl BotConfig {
         self.pin_all.unwrap_or_default()
     }
 
+    pub fn crosspost_all(&self) -> bool {
+        self.crosspost_all.unwrap_or_default()
+    }
+
+    /// Whether scheduled messages are sent without notifying anyone
+    pub fn silent_all(&self) -> bool {
+        self.silent_all.unwrap_or_default()
+    }
+
+    /// Whether archived threads are searched and unarchived when looking for a channel
+    pub fn search_archived_threads(&self) -> bool {
+        self.search_archived_threads.unwrap_or_default()
+    }
+
+    /// The pin count at which the oldest bot pinned message in a channel gets unpinned to make
+    /// room for a new one. Capped at the discord limit of 50 pins
+    pub fn unpin_oldest_at(&self) -> Option<usize> {
+        self.unpin_oldest_at.map(|limit| limit.min(MAX_PINS))
+    }
+
+    /// The allowed mentions of scheduled messages that do not set their own. Only users are
+    /// pinged if not set
+    pub fn allowed_mentions(&self) -> AllowedMentionsData {
+        self.allowed_mentions
+            .clone()
+            .unwrap_or_else(AllowedMentionsData::users_only)
+    }
+
+    /// Who can use the commands. Not set if the command permissions in discord are used instead
+    pub fn admins(&self) -> Option<Vec<AdminData>> {
+        self.admins.clone()
+    }
+
     pub fn get_token(&self) -> String {
         self.bot_token.clone()
     }
 
-    pub fn get_target_guild(&self) -> String {
+    pub fn get_target_guild(&self) -> Option<String> {
         self.target_guild.clone()
     }
 
-    pub fn get_target_channel(&self) -> String {
+    pub fn get_target_guild_id(&self) -> Option<GuildId> {
+        self.target_guild_id
+    }
+
+    pub fn get_target_channel(&self) -> Option<String> {
         self.target_channel.clone()
     }
+
+    pub fn get_target_channel_id(&self) -> Option<ChannelId> {
+        self.target_channel_id
+    }
 }
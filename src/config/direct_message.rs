@@ -0,0 +1,52 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::UserId;
+use std::fs::File;
+use std::io::Write;
+
+use super::{parse_config, read_optional_config, ConfigError};
+
+/// A direct message of a scheduled message that could not be delivered to a recipient
+#[derive(Deserialize, Serialize, Clone)]
+pub struct FailedDirectMessage {
+    pub schedule_id: u32,
+    pub user_id: UserId,
+    pub dms_closed: bool,
+    pub reason: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Keeps track of every recipient a direct message could not be delivered to
+#[derive(Deserialize, Serialize, Default)]
+pub struct FailedDirectMessages {
+    pub failed: Vec<FailedDirectMessage>,
+}
+
+impl FailedDirectMessages {
+    pub fn add_new_failed(&mut self, failed: FailedDirectMessage) {
+        self.failed.push(failed);
+    }
+
+    /// Read dm_failed.json. A missing file is treated as no failed direct messages
+    pub fn get_failed_direct_messages() -> Result<FailedDirectMessages, ConfigError> {
+        let json_string = match read_optional_config("dm_failed.json")? {
+            Some(json_string) => json_string,
+            None => return Ok(FailedDirectMessages::default()),
+        };
+
+        let result: FailedDirectMessages = parse_config("dm_failed.json", &json_string)?;
+        Ok(result)
+    }
+
+    pub fn save_failed_direct_messages(&self) -> Result<(), Error> {
+        let serialized_data = serde_json::to_string(self).context("Failed to serialize data")?;
+
+        let mut file =
+            File::create("config/dm_failed.json").context("Failed to create dm_failed.json")?;
+
+        file.write_all(serialized_data.as_bytes())
+            .context("Failed to write to dm_failed.json")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+
+const MAX_VALUE_LENGTH: usize = 60;
+
+/// Why a config file could not be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be opened or read
+    Io {
+        file: String,
+        source: std::io::Error,
+    },
+    /// The file is not valid JSON or does not match the expected fields
+    Parse {
+        file: String,
+        line: usize,
+        column: usize,
+        path: String,
+        value: Option<String>,
+        message: String,
+    },
+    /// The file was parsed but the values in it cannot be used
+    Invalid { file: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { file, source } => write!(f, "Failed to read {file}. Reason: {source}"),
+            Self::Parse {
+                file,
+                line,
+                column,
+                path,
+                value,
+                message,
+            } => {
+                write!(f, "Failed to parse {file} at line {line} column {column}")?;
+
+                if !path.is_empty() {
+                    write!(f, " ({path})")?;
+                }
+
+                write!(f, ": {message}")?;
+
+                if let Some(value) = value {
+                    write!(f, ". Found {value}")?;
+                }
+
+                Ok(())
+            }
+            Self::Invalid { file, message } => write!(f, "{file}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Read a file in the config folder
+pub fn read_config(file: &str) -> Result<String, ConfigError> {
+    fs::read_to_string(format!("config/{file}")).map_err(|source| ConfigError::Io {
+        file: file.to_string(),
+        source,
+    })
+}
+
+/// Read a file in the config folder that is created by the bot. A missing file is `None`
+pub fn read_optional_config(file: &str) -> Result<Option<String>, ConfigError> {
+    match read_config(file) {
+        Ok(json_string) => Ok(Some(json_string)),
+        Err(ConfigError::Io { source, .. }) if source.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parse the content of a config file. A failure points at the line, column and JSON path of the
+/// problem along with the value found there
+pub fn parse_config<T: DeserializeOwned>(file: &str, json_string: &str) -> Result<T, ConfigError> {
+    serde_json::from_str(json_string).map_err(|e| {
+        let line = e.line();
+        let column = e.column();
+        let offset = position_offset(json_string, line, column);
+        let (path, value) = locate(&json_string[..offset]);
+
+        // The message of serde_json ends with the position which is already part of the error
+        let message = e.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map(|(message, _position)| message.to_string())
+            .unwrap_or(message);
+
+        ConfigError::Parse {
+            file: file.to_string(),
+            line,
+            column,
+            path,
+            value: value.filter(|_| e.classify() == Category::Data),
+            message,
+        }
+    })
+}
+
+/// The byte offset of a 1-based line and column given by serde_json. The column is the number of
+/// bytes read on that line
+fn position_offset(json_string: &str, line: usize, column: usize) -> usize {
+    let line_start = json_string
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+
+    let mut offset = (line_start + column).min(json_string.len());
+
+    while !json_string.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+/// One level of nesting while walking the JSON text
+enum Level {
+    Array(usize),
+    Object { key: Option<String>, in_key: bool },
+}
+
+/// Walk the JSON text up to where parsing stopped and return the path of the value being parsed
+/// there, such as `[3].scheduled_at`, along with that value if it was the last thing read
+fn locate(parsed: &str) -> (String, Option<String>) {
+    let mut levels: Vec<Level> = Vec::new();
+    let mut last_value = None;
+    let mut chars = parsed.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' => {
+                levels.push(Level::Object {
+                    key: None,
+                    in_key: true,
+                });
+                last_value = None;
+            }
+            '[' => {
+                levels.push(Level::Array(0));
+                last_value = None;
+            }
+            '}' | ']' => {
+                levels.pop();
+                last_value = None;
+            }
+            ',' => {
+                match levels.last_mut() {
+                    Some(Level::Array(index)) => *index += 1,
+                    Some(Level::Object { in_key, .. }) => *in_key = true,
+                    None => {}
+                }
+                last_value = None;
+            }
+            ':' => {
+                if let Some(Level::Object { in_key, .. }) = levels.last_mut() {
+                    *in_key = false;
+                }
+            }
+            '"' => {
+                let mut end = parsed.len();
+                let mut escaped = false;
+
+                for (index, c) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        end = index + 1;
+                        break;
+                    }
+                }
+
+                let text = &parsed[start..end];
+
+                match levels.last_mut() {
+                    Some(Level::Object { key, in_key: true }) => {
+                        *key = Some(text.trim_matches('"').to_string());
+                    }
+                    _ => last_value = Some(text.to_string()),
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                // A number, true, false or null
+                let mut end = start + c.len_utf8();
+
+                while let Some((index, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, ',' | '}' | ']' | ':') {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+
+                last_value = Some(parsed[start..end].to_string());
+            }
+        }
+    }
+
+    let mut path = String::new();
+
+    for level in &levels {
+        match level {
+            Level::Array(index) => path.push_str(&format!("[{index}]")),
+            Level::Object { key: Some(key), .. } => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+            }
+            Level::Object { key: None, .. } => {}
+        }
+    }
+
+    let value = last_value.map(|value| {
+        if value.chars().count() > MAX_VALUE_LENGTH {
+            let mut shortened = value.chars().take(MAX_VALUE_LENGTH).collect::<String>();
+            shortened.push_str("...");
+            shortened
+        } else {
+            value
+        }
+    });
+
+    (path, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Entry {
+        id: u32,
+        text: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Grid {
+        rows: Vec<Vec<u32>>,
+    }
+
+    fn parse_error<T: DeserializeOwned + std::fmt::Debug>(
+        json_string: &str,
+    ) -> (String, Option<String>, String) {
+        match parse_config::<T>("test.json", json_string).unwrap_err() {
+            ConfigError::Parse {
+                path,
+                value,
+                message,
+                ..
+            } => (path, value, message),
+            e => panic!("expected a parse error, got {e}"),
+        }
+    }
+
+    #[test]
+    fn path_skips_string_escapes() {
+        let json_string = r#"[{"text": "a \"quoted\", [braced] {value}", "id": "bad"}]"#;
+        let (path, value, _message) = parse_error::<Vec<Entry>>(json_string);
+
+        assert_eq!(path, "[0].id");
+        assert_eq!(value.as_deref(), Some(r#""bad""#));
+    }
+
+    #[test]
+    fn path_follows_nested_arrays() {
+        let json_string = "{\n    \"rows\": [[1, 2], [3, \"four\"]]\n}";
+        let (path, value, _message) = parse_error::<Grid>(json_string);
+
+        assert_eq!(path, "rows[1][1]");
+        assert_eq!(value.as_deref(), Some(r#""four""#));
+    }
+
+    #[test]
+    fn missing_field_points_at_the_object() {
+        let json_string = "[\n    {\"id\": 1},\n    {\"text\": \"no id\"}\n]";
+        let (path, value, message) = parse_error::<Vec<Entry>>(json_string);
+
+        assert_eq!(path, "[1]");
+        assert_eq!(value, None);
+        assert!(message.contains("missing field `id`"));
+    }
+
+    #[test]
+    fn multibyte_text_before_the_error() {
+        let json_string = r#"[{"text": "héllo wörld ✓", "id": -1}]"#;
+        let (path, value, _message) = parse_error::<Vec<Entry>>(json_string);
+
+        assert_eq!(path, "[0].id");
+        assert_eq!(value.as_deref(), Some("-1"));
+    }
+
+    #[test]
+    fn offset_stays_on_a_char_boundary() {
+        let json_string = "{\n\"é\": 1}";
+
+        assert_eq!(position_offset(json_string, 2, 0), 2);
+        // The second column is in the middle of é so the offset moves back to its start
+        assert_eq!(position_offset(json_string, 2, 2), 3);
+        assert_eq!(position_offset(json_string, 2, 3), 5);
+        assert_eq!(position_offset(json_string, 2, 100), json_string.len());
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, ScheduledEventId};
+use std::fs::File;
+use std::io::Write;
+
+use super::{parse_config, read_optional_config, ConfigError};
+
+/// A guild scheduled event that is created by the bot at the given time
+#[derive(Deserialize, Clone)]
+pub struct EventData {
+    id: u32,
+    name: String,
+    description: Option<String>,
+    create_at: DateTime<Utc>,
+    start_at: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+    pub location: Option<String>,
+    pub voice_channel: Option<String>,
+    pub voice_channel_id: Option<ChannelId>,
+    pub target_guild: Option<String>,
+    pub target_guild_id: Option<GuildId>,
+    pub image: Option<String>,
+}
+
+impl EventData {
+    /// Read events.json. A missing file is treated as no events
+    pub fn get_all_events() -> Result<Vec<EventData>, ConfigError> {
+        let json_string = match read_optional_config("events.json")? {
+            Some(json_string) => json_string,
+            None => return Ok(Vec::new()),
+        };
+
+        let result: Vec<EventData> = parse_config("events.json", &json_string)?;
+        Ok(result)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    pub fn create_at(&self) -> DateTime<Utc> {
+        self.create_at
+    }
+
+    pub fn start_at(&self) -> DateTime<Utc> {
+        self.start_at
+    }
+
+    pub fn end_at(&self) -> Option<DateTime<Utc>> {
+        self.end_at
+    }
+
+    /// Whether a voice or stage channel is set by name or id
+    pub fn has_voice_channel(&self) -> bool {
+        self.voice_channel.is_some() || self.voice_channel_id.is_some()
+    }
+
+    /// Whether a guild is set by name or id
+    pub fn has_target_guild(&self) -> bool {
+        self.target_guild.is_some() || self.target_guild_id.is_some()
+    }
+}
+
+/// A guild scheduled event that was created by the bot
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CreatedEvent {
+    pub event_id: u32,
+    pub guild_id: GuildId,
+    pub scheduled_event_id: ScheduledEventId,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CreatedEvent {
+    /// The link that opens the event in discord
+    pub fn link(&self) -> String {
+        format!(
+            "https://discord.com/events/{}/{}",
+            self.guild_id, self.scheduled_event_id
+        )
+    }
+}
+
+/// Keeps track of the events that have been created so each one is only created once
+#[derive(Deserialize, Serialize, Default)]
+pub struct CreatedEvents {
+    pub created: Vec<CreatedEvent>,
+}
+
+impl CreatedEvents {
+    pub fn add_new_created(&mut self, created: CreatedEvent) {
+        self.created.push(created);
+    }
+
+    /// The created guild event of the event with the given id
+    pub fn get_created(&self, event_id: u32) -> Option<CreatedEvent> {
+        self.created
+            .iter()
+            .find(|created| created.event_id == event_id)
+            .cloned()
+    }
+
+    /// Read created_events.json. A missing file is treated as no created events
+    pub fn get_created_events() -> Result<CreatedEvents, ConfigError> {
+        let json_string = match read_optional_config("created_events.json")? {
+            Some(json_string) => json_string,
+            None => return Ok(CreatedEvents::default()),
+        };
+
+        let result: CreatedEvents = parse_config("created_events.json", &json_string)?;
+        Ok(result)
+    }
+
+    pub fn save_created_events(&self) -> Result<(), Error> {
+        let serialized_data = serde_json::to_string(self).context("Failed to serialize data")?;
+
+        let mut file = File::create("config/created_events.json")
+            .context("Failed to create created_events.json")?;
+
+        file.write_all(serialized_data.as_bytes())
+            .context("Failed to write to created_events.json")?;
+        Ok(())
+    }
+}
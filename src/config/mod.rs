@@ -1,9 +1,23 @@
 mod bot_config;
+mod direct_message;
+mod error;
+mod event;
+mod pinned;
 mod poll;
 mod quiz;
 mod schedule;
+mod sent;
 
-pub use bot_config::BotConfig;
+pub use bot_config::{AdminData, BotConfig};
+pub use direct_message::{FailedDirectMessage, FailedDirectMessages};
+pub use error::ConfigError;
+use error::{parse_config, read_config, read_optional_config};
+pub use event::{CreatedEvent, CreatedEvents, EventData};
+pub use pinned::{PinnedMessage, PinnedMessages};
 pub use poll::PollData;
 pub use quiz::QuizData;
-pub use schedule::{CompletedScheduled, ScheduledMessage};
+pub use schedule::{
+    AllowedMentionsData, CompletedScheduled, RelativeData, ScheduledMessage, TargetData,
+    ThreadData, WebhookData,
+};
+pub use sent::{SentMessage, SentMessages};
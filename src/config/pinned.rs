@@ -0,0 +1,74 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, MessageId};
+use std::fs::File;
+use std::io::Write;
+
+use super::{parse_config, read_optional_config, ConfigError};
+
+/// A message that was pinned by the bot itself
+#[derive(Deserialize, Serialize, Clone)]
+pub struct PinnedMessage {
+    pub schedule_id: u32,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub pinned_at: DateTime<Utc>,
+    pub unpin_at: Option<DateTime<Utc>>,
+}
+
+/// Keeps track of all messages pinned by the bot. Only messages in here are ever unpinned.
+#[derive(Deserialize, Serialize, Default)]
+pub struct PinnedMessages {
+    pub pinned: Vec<PinnedMessage>,
+}
+
+impl PinnedMessages {
+    pub fn add_new_pinned(&mut self, pinned: PinnedMessage) {
+        self.pinned.push(pinned);
+    }
+
+    pub fn remove_pinned(&mut self, message_id: MessageId) {
+        self.pinned.retain(|pinned| pinned.message_id != message_id);
+    }
+
+    /// The pinned messages whose unpin time has been reached
+    pub fn expired(&self, now: DateTime<Utc>) -> Vec<PinnedMessage> {
+        self.pinned
+            .iter()
+            .filter(|pinned| pinned.unpin_at.is_some_and(|unpin_at| now >= unpin_at))
+            .cloned()
+            .collect()
+    }
+
+    /// The oldest message pinned by the bot in the given channel
+    pub fn oldest_in_channel(&self, channel_id: ChannelId) -> Option<PinnedMessage> {
+        self.pinned
+            .iter()
+            .filter(|pinned| pinned.channel_id == channel_id)
+            .min_by_key(|pinned| pinned.pinned_at)
+            .cloned()
+    }
+
+    /// Read pinned.json. A missing file is treated as no pinned messages
+    pub fn get_pinned_messages() -> Result<PinnedMessages, ConfigError> {
+        let json_string = match read_optional_config("pinned.json")? {
+            Some(json_string) => json_string,
+            None => return Ok(PinnedMessages::default()),
+        };
+
+        let result: PinnedMessages = parse_config("pinned.json", &json_string)?;
+        Ok(result)
+    }
+
+    pub fn save_pinned_messages(&self) -> Result<(), Error> {
+        let serialized_data = serde_json::to_string(self).context("Failed to serialize data")?;
+
+        let mut file =
+            File::create("config/pinned.json").context("Failed to create pinned.json")?;
+
+        file.write_all(serialized_data.as_bytes())
+            .context("Failed to write to pinned.json")?;
+        Ok(())
+    }
+}
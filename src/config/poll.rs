@@ -1,7 +1,7 @@
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, Error};
 use serde::Deserialize;
-use std::fs::File;
-use std::io::Read;
+
+use super::{parse_config, read_config, ConfigError};
 
 #[derive(Deserialize, Clone)]
 pub struct PollData {
@@ -26,29 +26,16 @@ impl PollData {
     }
 
     pub fn duration_minutes(&self) -> u64 {
-        if let Some(min) = self.duration_minutes {
-            min
-        } else {
-            1440
-        }
+        self.duration_minutes.unwrap_or(1440)
     }
 
     pub fn multi_answer(&self) -> bool {
-        if let Some(multi) = self.multi_answer {
-            multi
-        } else {
-            false
-        }
+        self.multi_answer.unwrap_or_default()
     }
 
-    pub fn get_all_polls() -> Result<Vec<PollData>, Error> {
-        let mut file = File::open("config/poll.json").context("Failed to open poll.json file")?;
-        let mut json_string = String::new();
-
-        file.read_to_string(&mut json_string)
-            .context("Failed to read poll.json")?;
-        let result: Vec<PollData> =
-            serde_json::from_str(&json_string).context("Failed to parse poll.json file")?;
+    pub fn get_all_polls() -> Result<Vec<PollData>, ConfigError> {
+        let json_string = read_config("poll.json")?;
+        let result: Vec<PollData> = parse_config("poll.json", &json_string)?;
         Ok(result)
     }
 
@@ -1,9 +1,9 @@
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, Error};
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use serenity::model::id::ChannelId;
-use std::fs::File;
-use std::io::Read;
+use serenity::model::id::{ChannelId, GuildId};
+
+use super::{parse_config, read_config, ConfigError};
 
 #[derive(Deserialize, Clone)]
 pub struct QuizData {
@@ -12,12 +12,33 @@ pub struct QuizData {
     reply_with: String,
     end_at: Option<DateTime<Utc>>,
     pub monitor_guild: Option<String>,
+    pub monitor_guild_id: Option<GuildId>,
     pub monitor_channel: Option<String>,
+    pub monitor_channel_id: Option<ChannelId>,
+    monitor_thread: Option<bool>,
+    reply_ping: Option<bool>,
     #[serde(skip_deserializing)]
-    monitor_channel_id: ChannelId,
+    resolved_channel_id: ChannelId,
 }
 
 impl QuizData {
+    /// A quiz that is not part of quiz.json, started with a slash command
+    pub fn new_one_off(answer: String, reply_with: String, end_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            id: 0,
+            answer,
+            reply_with,
+            end_at,
+            monitor_guild: None,
+            monitor_guild_id: None,
+            monitor_channel: None,
+            monitor_channel_id: None,
+            monitor_thread: None,
+            reply_ping: None,
+            resolved_channel_id: ChannelId::default(),
+        }
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
@@ -34,15 +55,19 @@ impl QuizData {
         self.reply_with.clone()
     }
 
-    pub fn get_all_quiz_data() -> Result<Vec<QuizData>, Error> {
-        let mut file = File::open("config/quiz.json").context("Failed to open quiz.json")?;
-        let mut json_string = String::new();
+    /// Whether the thread started from the scheduled message should be monitored instead
+    pub fn monitor_thread(&self) -> bool {
+        self.monitor_thread.unwrap_or_default()
+    }
 
-        file.read_to_string(&mut json_string)
-            .context("Failed to read quiz.json")?;
+    /// Whether the reply to the winner pings them
+    pub fn reply_ping(&self) -> bool {
+        self.reply_ping.unwrap_or(true)
+    }
 
-        let result: Vec<QuizData> =
-            serde_json::from_str(&json_string).context("Failed to parse quiz.json")?;
+    pub fn get_all_quiz_data() -> Result<Vec<QuizData>, ConfigError> {
+        let json_string = read_config("quiz.json")?;
+        let result: Vec<QuizData> = parse_config("quiz.json", &json_string)?;
         Ok(result)
     }
 
@@ -58,14 +83,22 @@ impl QuizData {
     }
 
     pub fn guild_no_channel(&self) -> bool {
-        self.monitor_guild.is_some() && self.monitor_channel.is_none()
+        (self.monitor_guild.is_some() || self.monitor_guild_id.is_some())
+            && self.monitor_channel.is_none()
+            && self.monitor_channel_id.is_none()
+    }
+
+    /// Whether a different channel than the global target channel is set for monitoring
+    pub fn has_monitor_channel(&self) -> bool {
+        self.monitor_channel.is_some() || self.monitor_channel_id.is_some()
     }
 
+    /// The channel that was found to be monitored for the answer
     pub fn get_monitor_channel_id(&self) -> ChannelId {
-        self.monitor_channel_id
+        self.resolved_channel_id
     }
 
     pub fn set_monitor_channel_id(&mut self, channel_id: ChannelId) {
-        self.monitor_channel_id = channel_id
+        self.resolved_channel_id = channel_id
     }
 }
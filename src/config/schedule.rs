@@ -1,75 +1,567 @@
-use anyhow::{Context, Error};
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{Read, Write};
-
-#[derive(Deserialize, Clone)]
+use anyhow::{anyhow, Context, Error};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::ser::PrettyFormatter;
+use serenity::model::id::{ChannelId, GuildId, ScheduledEventId, UserId};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+
+use super::{parse_config, read_config, ConfigError};
+use crate::utils::minutes_later;
+
+const MAX_MESSAGE_LENGTH: usize = 2000;
+const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+const MAX_EMBED_TITLE_LENGTH: usize = 256;
+
+#[derive(Deserialize, Serialize, Clone, Default)]
 pub struct ScheduledMessage {
     id: u32,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub attachments: Option<Vec<String>>,
-    scheduled_at: DateTime<Utc>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_scheduled_at"
+    )]
+    scheduled_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub poll_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub quiz_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub to_pin: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unpin_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub target_guild: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_guild_id: Option<GuildId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub target_channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_channel_id: Option<ChannelId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread: Option<ThreadData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forum_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forum_tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crosspost: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<WebhookData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dm_users: Option<Vec<UserId>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dm_role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<TargetData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_mentions: Option<AllowedMentionsData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silent: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress_embeds: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to_schedule: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reactions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_event: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative_to: Option<RelativeData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed_title: Option<String>,
 }
 
-impl ScheduledMessage {
-    pub fn get_all_scheduled_messages() -> Result<Vec<Self>, Error> {
-        let mut file =
-            File::open("config/schedule.json").context("Failed to open schedule.json file")?;
-        let mut json_string = String::new();
+/// A channel the scheduled message is sent to, by name or id. Without a guild, the global target
+/// guild is used
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TargetData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<GuildId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<ChannelId>,
+}
+
+impl TargetData {
+    /// Identifies the target when tracking completion of each target
+    pub fn key(&self) -> String {
+        let guild = if let Some(id) = self.guild_id {
+            id.to_string()
+        } else {
+            self.guild.clone().unwrap_or_default()
+        };
+
+        let channel = if let Some(id) = self.channel_id {
+            id.to_string()
+        } else {
+            self.channel.clone().unwrap_or_default()
+        };
+
+        if guild.is_empty() {
+            channel
+        } else {
+            format!("{guild}/{channel}")
+        }
+    }
+
+    /// A readable name of the target channel for logging
+    pub fn channel_name(&self) -> String {
+        if let Some(name) = &self.channel {
+            name.clone()
+        } else {
+            self.channel_id.map(|id| id.to_string()).unwrap_or_default()
+        }
+    }
+}
+
+/// Write the scheduled time in the same format as the example config
+fn serialize_scheduled_at<S: Serializer>(
+    scheduled_at: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match scheduled_at {
+        Some(time) => serializer.serialize_str(&time.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Sends the scheduled message a number of minutes before or after a guild event starts. The
+/// event is searched in the given guild or the global target guild
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RelativeData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<ScheduledEventId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<GuildId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minutes_before: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minutes_after: Option<u64>,
+}
+
+impl RelativeData {
+    /// How far the message is from the start of the event. Negative if it is before the event.
+    /// `None` if the minutes are too large to be used
+    pub fn offset(&self) -> Option<TimeDelta> {
+        let before = i64::try_from(self.minutes_before.unwrap_or_default()).ok()?;
+        let after = i64::try_from(self.minutes_after.unwrap_or_default()).ok()?;
+
+        TimeDelta::try_minutes(after.checked_sub(before)?)
+    }
+
+    /// Whether both minutes_before and minutes_after are set
+    pub fn has_both_offsets(&self) -> bool {
+        self.minutes_before.is_some() && self.minutes_after.is_some()
+    }
+
+    /// Whether a guild is set by name or id
+    pub fn has_guild(&self) -> bool {
+        self.guild.is_some() || self.guild_id.is_some()
+    }
+}
+
+/// A thread that will be started from the scheduled message once it is sent
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ThreadData {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_archive_minutes: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slowmode_seconds: Option<u16>,
+}
 
-        file.read_to_string(&mut json_string)
-            .context("Failed to read schedule.json")
-            .context("Failed to read schedule.json")?;
+impl ThreadData {
+    /// The thread name with `{id}` and `{date}` replaced by the scheduled message id and the
+    /// scheduled date
+    pub fn name(&self, id: u32, scheduled_at: DateTime<Utc>) -> String {
+        self.name
+            .replace("{id}", &id.to_string())
+            .replace("{date}", &scheduled_at.format("%Y-%m-%d").to_string())
+    }
+
+    pub fn auto_archive_minutes(&self) -> u16 {
+        self.auto_archive_minutes.unwrap_or(1440)
+    }
 
-        let result: Vec<ScheduledMessage> =
-            serde_json::from_str(&json_string).context("Failed to parse schedule.json file")?;
+    pub fn slowmode_seconds(&self) -> u16 {
+        self.slowmode_seconds.unwrap_or_default()
+    }
+}
+
+impl ScheduledMessage {
+    pub fn get_all_scheduled_messages() -> Result<Vec<Self>, ConfigError> {
+        let json_string = read_config("schedule.json")?;
+        let result: Vec<ScheduledMessage> = parse_config("schedule.json", &json_string)?;
         Ok(result)
     }
 
+    /// A scheduled message that is sent to the global target channel at the given time
+    pub fn new(id: u32, message: String, scheduled_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            message,
+            scheduled_at: Some(scheduled_at),
+            ..Default::default()
+        }
+    }
+
+    /// Write all scheduled messages to schedule.json with the same indentation as the example
+    /// config. The data is written to a temporary file first which then replaces schedule.json, so
+    /// it is never read half written
+    pub fn save_all_scheduled_messages(messages: &[ScheduledMessage]) -> Result<(), Error> {
+        let mut data = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(
+            &mut data,
+            PrettyFormatter::with_indent(b"    "),
+        );
+        messages
+            .serialize(&mut serializer)
+            .context("Failed to serialize data")?;
+        data.push(b'\n');
+
+        let mut file = File::create("config/schedule.json.tmp")
+            .context("Failed to create schedule.json.tmp")?;
+
+        file.write_all(&data)
+            .context("Failed to write to schedule.json.tmp")?;
+        file.sync_all()
+            .context("Failed to write to schedule.json.tmp")?;
+
+        fs::rename("config/schedule.json.tmp", "config/schedule.json")
+            .context("Failed to replace schedule.json")?;
+        Ok(())
+    }
+
+    /// The id after the highest one in use. Completed ids are included so a removed scheduled
+    /// message does not get its id reused and marked as completed right away
+    pub fn next_free_id(messages: &[ScheduledMessage], completed: &CompletedScheduled) -> u32 {
+        let highest_scheduled = messages.iter().map(|message| message.id).max();
+        let highest_completed = completed.completed.iter().copied().max();
+
+        highest_scheduled.max(highest_completed).unwrap_or_default() + 1
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
 
+    pub fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
     pub fn message(&self) -> String {
         self.message.clone()
     }
 
-    pub fn scheduled_at(&self) -> DateTime<Utc> {
+    /// The fixed time of the message. Not set if the message is relative to a guild event
+    pub fn scheduled_at(&self) -> Option<DateTime<Utc>> {
         self.scheduled_at
     }
 
     pub fn guild_no_channel(&self) -> bool {
-        self.target_guild.is_some() && self.target_channel.is_none()
+        (self.target_guild.is_some() || self.target_guild_id.is_some())
+            && self.target_channel.is_none()
+            && self.target_channel_id.is_none()
+            && self.dm_role.is_none()
+    }
+
+    /// Whether a single target channel is set by name or id
+    pub fn has_target_channel(&self) -> bool {
+        self.target_channel.is_some() || self.target_channel_id.is_some()
+    }
+
+    /// Whether a single target guild is set by name or id
+    pub fn has_target_guild(&self) -> bool {
+        self.target_guild.is_some() || self.target_guild_id.is_some()
+    }
+
+    /// The channels the message is sent to. Empty if it is sent to the global target channel
+    pub fn get_targets(&self) -> Vec<TargetData> {
+        if let Some(targets) = &self.targets {
+            return targets.clone();
+        }
+
+        if self.target_channel.is_some() || self.target_channel_id.is_some() {
+            return vec![TargetData {
+                guild: self.target_guild.clone(),
+                guild_id: self.target_guild_id,
+                channel: self.target_channel.clone(),
+                channel_id: self.target_channel_id,
+            }];
+        }
+
+        Vec::new()
     }
+
+    /// Whether the message was cancelled and will not be sent
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.unwrap_or_default()
+    }
+
+    /// Whether the message is sent to the given channel, by its id or name. A message without
+    /// targets is sent to the given channel if it is the global target channel
+    pub fn sends_to_channel(
+        &self,
+        channel_id: ChannelId,
+        channel_name: Option<&str>,
+        is_target_channel: bool,
+    ) -> bool {
+        let targets = self.get_targets();
+
+        if targets.is_empty() {
+            return is_target_channel && !self.is_direct_message();
+        }
+
+        targets.iter().any(|target| {
+            target.channel_id == Some(channel_id)
+                || (target.channel_id.is_none()
+                    && channel_name.is_some()
+                    && target.channel.as_deref() == channel_name)
+        })
+    }
+
+    /// Whether the message is sent as direct messages instead of to a channel
+    pub fn is_direct_message(&self) -> bool {
+        self.dm_users.is_some() || self.dm_role.is_some()
+    }
+
+    /// A copy of the message that is safe to show. The webhook url contains the token of the
+    /// webhook so it is hidden
+    pub fn redacted(&self) -> Self {
+        let mut message = self.clone();
+
+        if let Some(webhook) = &mut message.webhook {
+            if webhook.url.is_some() {
+                webhook.url = Some("<hidden>".to_string());
+            }
+        }
+
+        message
+    }
+
+    /// Check that the message has exactly one way of getting its send time
+    pub fn validate_time(&self) -> Result<(), Error> {
+        if self.scheduled_at.is_some() == self.relative_to.is_some() {
+            return Err(anyhow!(
+                "Exactly one of scheduled_at or relative_to must be provided"
+            ));
+        }
+
+        if let Some(relative) = &self.relative_to {
+            if relative.event.is_none() && relative.event_id.is_none() {
+                return Err(anyhow!("relative_to must have an event or event_id"));
+            }
+
+            if relative.has_both_offsets() {
+                return Err(anyhow!(
+                    "minutes_before and minutes_after cannot be used together"
+                ));
+            }
+
+            if relative.offset().is_none() {
+                return Err(anyhow!("minutes_before or minutes_after is too large"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the fields of the message that cannot be used together and the length limits of
+    /// discord. Anything that needs the guild data, such as the target channel, is not checked
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_time()?;
+
+        if self.guild_no_channel() {
+            return Err(anyhow!(
+                "target_guild was provided but no target_channel was found"
+            ));
+        }
+
+        if self.is_direct_message() && (self.has_target_channel() || self.targets.is_some()) {
+            return Err(anyhow!(
+                "target_channel or targets cannot be used along with dm_users or dm_role"
+            ));
+        }
+
+        if self.reply_to.is_some() && self.reply_to_schedule.is_some() {
+            return Err(anyhow!(
+                "reply_to and reply_to_schedule cannot be used together"
+            ));
+        }
+
+        if self.is_direct_message() && (self.reply_to.is_some() || self.reply_to_schedule.is_some())
+        {
+            return Err(anyhow!("A direct message cannot reply to a message"));
+        }
+
+        if self.targets.is_some() && (self.has_target_guild() || self.has_target_channel()) {
+            return Err(anyhow!(
+                "targets cannot be used along with target_guild or target_channel"
+            ));
+        }
+
+        // A webhook url belongs to a single channel, so every target would get sent to it
+        if self
+            .webhook
+            .as_ref()
+            .is_some_and(|webhook| webhook.url.is_some())
+            && self.get_targets().len() > 1
+        {
+            return Err(anyhow!(
+                "webhook url cannot be used along with more than one target"
+            ));
+        }
+
+        if self
+            .unpin_after
+            .is_some_and(|minutes| minutes_later(Utc::now(), minutes).is_none())
+        {
+            return Err(anyhow!("unpin_after is too large"));
+        }
+
+        let length = self.message.chars().count();
+
+        if let Some(title) = &self.embed_title {
+            if title.trim().is_empty() {
+                return Err(anyhow!("embed_title cannot be empty"));
+            }
+
+            if title.chars().count() > MAX_EMBED_TITLE_LENGTH {
+                return Err(anyhow!(
+                    "embed_title can be at most {MAX_EMBED_TITLE_LENGTH} characters"
+                ));
+            }
+
+            if length > MAX_EMBED_DESCRIPTION_LENGTH {
+                return Err(anyhow!(
+                    "message can be at most {MAX_EMBED_DESCRIPTION_LENGTH} characters in an embed"
+                ));
+            }
+        } else if length > MAX_MESSAGE_LENGTH {
+            return Err(anyhow!(
+                "message can be at most {MAX_MESSAGE_LENGTH} characters without embed_title"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Who can be pinged by the mentions in a message. Anything not set is not pinged
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct AllowedMentionsData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    everyone: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    users: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    all_roles: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roles: Option<Vec<String>>,
+}
+
+impl AllowedMentionsData {
+    /// Only pings the mentioned users. Used when no allowed mentions are set anywhere
+    pub fn users_only() -> Self {
+        Self {
+            users: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `@everyone` and `@here` ping
+    pub fn everyone(&self) -> bool {
+        self.everyone.unwrap_or_default()
+    }
+
+    pub fn users(&self) -> bool {
+        self.users.unwrap_or_default()
+    }
+
+    pub fn all_roles(&self) -> bool {
+        self.all_roles.unwrap_or_default()
+    }
+
+    /// The names of the roles that can be pinged
+    pub fn roles(&self) -> Vec<String> {
+        self.roles.clone().unwrap_or_default()
+    }
+}
+
+/// Send the scheduled message over a webhook with a custom name and avatar. Without a url, a
+/// webhook managed by the bot in the target channel is used
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WebhookData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Default)]
 pub struct CompletedScheduled {
     pub completed: HashSet<u32>,
+    /// Targets that have been completed for scheduled messages with multiple targets that are not
+    /// completed yet
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub completed_targets: HashMap<u32, HashSet<String>>,
 }
 
 impl CompletedScheduled {
     pub fn add_new_completed(&mut self, id: u32) {
         self.completed.insert(id);
+        self.completed_targets.remove(&id);
     }
 
-    pub fn get_completed_scheduled() -> Result<CompletedScheduled, Error> {
-        let mut file =
-            File::open("config/completed.json").context("Failed to open completed.json")?;
-        let mut json_string = String::new();
+    pub fn add_completed_target(&mut self, id: u32, target: String) {
+        self.completed_targets.entry(id).or_default().insert(target);
+    }
+
+    /// Add everything that is completed in the other data, such as what another task saved since
+    /// this data was read
+    pub fn merge(&mut self, other: CompletedScheduled) {
+        self.completed.extend(other.completed);
+
+        for (id, targets) in other.completed_targets {
+            self.completed_targets
+                .entry(id)
+                .or_default()
+                .extend(targets);
+        }
 
-        file.read_to_string(&mut json_string)
-            .context("Failed to read completed.json")?;
+        let completed = &self.completed;
+        self.completed_targets
+            .retain(|id, _| !completed.contains(id));
+    }
+
+    pub fn target_completed(&self, id: u32, target: &str) -> bool {
+        self.completed_targets
+            .get(&id)
+            .is_some_and(|targets| targets.contains(target))
+    }
 
-        let result: CompletedScheduled =
-            serde_json::from_str(&json_string).context("Failed to parse completed.json file")?;
+    pub fn get_completed_scheduled() -> Result<CompletedScheduled, ConfigError> {
+        let json_string = read_config("completed.json")?;
+        let result: CompletedScheduled = parse_config("completed.json", &json_string)?;
         Ok(result)
     }
 
@@ -0,0 +1,63 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, MessageId};
+use std::fs::File;
+use std::io::Write;
+
+use super::{parse_config, read_optional_config, ConfigError};
+
+/// A message that was sent by the bot for a scheduled message
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SentMessage {
+    pub schedule_id: u32,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Keeps track of the latest message sent for every scheduled message in each channel so later
+/// scheduled messages can reply to it
+#[derive(Deserialize, Serialize, Default)]
+pub struct SentMessages {
+    pub sent: Vec<SentMessage>,
+}
+
+impl SentMessages {
+    /// Add a sent message, replacing the earlier one of the same scheduled message in the channel
+    pub fn add_new_sent(&mut self, sent: SentMessage) {
+        self.sent.retain(|existing| {
+            existing.schedule_id != sent.schedule_id || existing.channel_id != sent.channel_id
+        });
+        self.sent.push(sent);
+    }
+
+    /// The message sent for the scheduled message in the given channel
+    pub fn sent_in_channel(&self, schedule_id: u32, channel_id: ChannelId) -> Option<SentMessage> {
+        self.sent
+            .iter()
+            .find(|sent| sent.schedule_id == schedule_id && sent.channel_id == channel_id)
+            .cloned()
+    }
+
+    /// Read sent.json. A missing file is treated as no sent messages
+    pub fn get_sent_messages() -> Result<SentMessages, ConfigError> {
+        let json_string = match read_optional_config("sent.json")? {
+            Some(json_string) => json_string,
+            None => return Ok(SentMessages::default()),
+        };
+
+        let result: SentMessages = parse_config("sent.json", &json_string)?;
+        Ok(result)
+    }
+
+    pub fn save_sent_messages(&self) -> Result<(), Error> {
+        let serialized_data = serde_json::to_string(self).context("Failed to serialize data")?;
+
+        let mut file = File::create("config/sent.json").context("Failed to create sent.json")?;
+
+        file.write_all(serialized_data.as_bytes())
+            .context("Failed to write to sent.json")?;
+        Ok(())
+    }
+}
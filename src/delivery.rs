@@ -0,0 +1,409 @@
+use anyhow::{anyhow, Error};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use serde::Serialize;
+use serenity::builder::create_poll::Ready;
+use serenity::builder::{
+    CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateForumPost, CreateMessage,
+    CreatePoll, CreateWebhook, ExecuteWebhook,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info};
+
+use crate::config::{
+    CompletedScheduled, FailedDirectMessage, FailedDirectMessages, ScheduledMessage, WebhookData,
+};
+use crate::utils::{
+    is_dm_closed, save_as_completed, save_target_as_completed, start_sending_direct_messages,
+    stop_sending_direct_messages, MAX_THREAD_NAME_LENGTH,
+};
+
+const MAX_FORUM_TAGS: usize = 5;
+const MANAGED_WEBHOOK_NAME: &str = "Pulse";
+const DM_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The content of a scheduled message, independent of how it gets delivered
+#[derive(Clone)]
+pub struct MessageContent {
+    content: String,
+    pub poll: Option<CreatePoll<Ready>>,
+    pub attachments: Vec<CreateAttachment>,
+    pub allowed_mentions: Option<CreateAllowedMentions>,
+    pub flags: MessageFlags,
+    pub tts: bool,
+    pub reference: Option<MessageReference>,
+    pub embed: Option<CreateEmbed>,
+}
+
+/// How many recipients a direct message was delivered to
+#[derive(Default)]
+pub struct DirectMessageSummary {
+    pub sent: usize,
+    pub dms_closed: usize,
+    pub failed: usize,
+}
+
+/// The webhook execution payload with the fields that are not supported by `ExecuteWebhook`
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    message: &'a ExecuteWebhook,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poll: Option<&'a CreatePoll<Ready>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    applied_tags: Vec<ForumTagId>,
+}
+
+impl MessageContent {
+    pub fn new(content: String) -> Self {
+        Self {
+            content,
+            poll: None,
+            attachments: Vec::new(),
+            allowed_mentions: None,
+            flags: MessageFlags::empty(),
+            tts: false,
+            reference: None,
+            embed: None,
+        }
+    }
+
+    /// A message sent as an embed with the given title and the content as its description
+    pub fn new_embed(title: String, content: String) -> Self {
+        let mut message = Self::new(String::new());
+        message.embed = Some(CreateEmbed::new().title(title).description(content));
+        message
+    }
+
+    pub fn to_message(&self) -> CreateMessage {
+        let mut message = CreateMessage::new()
+            .content(&self.content)
+            .add_files(self.attachments.clone())
+            .flags(self.flags)
+            .tts(self.tts);
+
+        if let Some(poll) = &self.poll {
+            message = message.poll(poll.clone());
+        }
+
+        if let Some(allowed_mentions) = &self.allowed_mentions {
+            message = message.allowed_mentions(allowed_mentions.clone());
+        }
+
+        if let Some(reference) = &self.reference {
+            message = message.reference_message(reference.clone());
+        }
+
+        if let Some(embed) = &self.embed {
+            message = message.embed(embed.clone());
+        }
+
+        message
+    }
+
+    /// The webhook version of the message. Poll is not part of it and must be sent separately
+    pub fn to_webhook(&self, webhook_data: &WebhookData) -> ExecuteWebhook {
+        let mut message = ExecuteWebhook::new()
+            .content(&self.content)
+            .add_files(self.attachments.clone())
+            .flags(self.flags)
+            .tts(self.tts);
+
+        if let Some(username) = &webhook_data.username {
+            message = message.username(username);
+        }
+
+        if let Some(avatar_url) = &webhook_data.avatar_url {
+            message = message.avatar_url(avatar_url);
+        }
+
+        if let Some(allowed_mentions) = &self.allowed_mentions {
+            message = message.allowed_mentions(allowed_mentions.clone());
+        }
+
+        if let Some(embed) = &self.embed {
+            message = message.embed(embed.clone());
+        }
+
+        message
+    }
+}
+
+/// Send a message to the target channel, over a webhook if the scheduled message has one. Forum
+/// channels get a new post with the forum title and tags of the scheduled message, returning the
+/// starting message of the post
+pub async fn send_to_target(
+    ctx: &Context,
+    channel: &GuildChannel,
+    content: &MessageContent,
+    scheduled: &ScheduledMessage,
+) -> Result<Message, Error> {
+    if channel.kind != ChannelType::Forum {
+        if let Some(webhook_data) = &scheduled.webhook {
+            return send_webhook(ctx, channel, content, webhook_data, None, Vec::new()).await;
+        }
+
+        let sent_message = channel.send_message(ctx, content.to_message()).await?;
+        return Ok(sent_message);
+    }
+
+    let title = scheduled
+        .forum_title
+        .as_ref()
+        .ok_or_else(|| anyhow!("forum_title is required when the target channel is a forum"))?;
+
+    if title.trim().is_empty() {
+        return Err(anyhow!("forum_title cannot be empty"));
+    }
+
+    let title = title
+        .chars()
+        .take(MAX_THREAD_NAME_LENGTH)
+        .collect::<String>();
+
+    let mut tag_ids = Vec::new();
+
+    if let Some(tags) = &scheduled.forum_tags {
+        if tags.len() > MAX_FORUM_TAGS {
+            return Err(anyhow!("A forum post can have at most 5 tags"));
+        }
+
+        for tag_name in tags {
+            let tag = channel
+                .available_tags
+                .iter()
+                .find(|tag| &tag.name == tag_name)
+                .ok_or_else(|| anyhow!("Forum tag {tag_name} was not found in the channel"))?;
+
+            tag_ids.push(tag.id);
+        }
+    }
+
+    if let Some(webhook_data) = &scheduled.webhook {
+        return send_webhook(ctx, channel, content, webhook_data, Some(title), tag_ids).await;
+    }
+
+    let mut post = CreateForumPost::new(title, content.to_message());
+
+    for tag_id in tag_ids {
+        post = post.add_applied_tag(tag_id);
+    }
+
+    let thread = channel.create_forum_post(ctx, post).await?;
+
+    // The starting message of a forum post shares the id of the post thread
+    let sent_message = thread
+        .id
+        .message(ctx, MessageId::new(thread.id.get()))
+        .await?;
+
+    Ok(sent_message)
+}
+
+/// Send a message over the webhook of the scheduled message. A forum title starts a new post with
+/// the given forum tags. Threads have no webhooks of their own so the webhook of the parent channel
+/// sends the message into the thread
+async fn send_webhook(
+    ctx: &Context,
+    channel: &GuildChannel,
+    content: &MessageContent,
+    webhook_data: &WebhookData,
+    forum_title: Option<String>,
+    tag_ids: Vec<ForumTagId>,
+) -> Result<Message, Error> {
+    let thread_id = channel.thread_metadata.map(|_| channel.id);
+
+    let webhook_channel = if thread_id.is_some() {
+        channel
+            .parent_id
+            .ok_or_else(|| anyhow!("The thread {} has no parent channel", channel.name))?
+    } else {
+        channel.id
+    };
+
+    let webhook = get_webhook(ctx, webhook_channel, webhook_data).await?;
+
+    let mut message = content.to_webhook(webhook_data);
+
+    if let Some(thread_id) = thread_id {
+        message = message.in_thread(thread_id);
+    }
+
+    if let Some(title) = forum_title {
+        message = message.thread_name(title);
+    }
+
+    // ExecuteWebhook has no support for polls or forum tags. Those are sent with a custom payload
+    // which does not support attachments
+    let sent_message = if content.poll.is_some() || !tag_ids.is_empty() {
+        if !content.attachments.is_empty() {
+            return Err(anyhow!(
+                "Attachments cannot be sent along with polls or forum tags over a webhook"
+            ));
+        }
+
+        let token = webhook
+            .token
+            .as_ref()
+            .ok_or_else(|| anyhow!("The webhook has no token"))?;
+
+        let payload = WebhookPayload {
+            message: &message,
+            poll: content.poll.as_ref(),
+            applied_tags: tag_ids,
+        };
+
+        ctx.http
+            .execute_webhook(
+                webhook.id,
+                thread_id,
+                token.expose_secret(),
+                true,
+                Vec::new(),
+                &payload,
+            )
+            .await?
+    } else {
+        webhook.execute(ctx, true, message).await?
+    };
+
+    sent_message.ok_or_else(|| anyhow!("The webhook did not return the sent message"))
+}
+
+/// Get the webhook from the url of the webhook data or the webhook managed by the bot in the
+/// channel. The managed webhook is created if it does not exist yet
+async fn get_webhook(
+    ctx: &Context,
+    channel_id: ChannelId,
+    webhook_data: &WebhookData,
+) -> Result<Webhook, Error> {
+    if let Some(url) = &webhook_data.url {
+        let webhook = Webhook::from_url(ctx, url).await?;
+        return Ok(webhook);
+    }
+
+    let bot_id = ctx.cache.current_user().id;
+    let webhooks = channel_id.webhooks(ctx).await?;
+
+    let managed = webhooks.into_iter().find(|webhook| {
+        webhook.name.as_deref() == Some(MANAGED_WEBHOOK_NAME)
+            && webhook.token.is_some()
+            && webhook.user.as_ref().is_some_and(|user| user.id == bot_id)
+    });
+
+    if let Some(webhook) = managed {
+        return Ok(webhook);
+    }
+
+    let webhook = channel_id
+        .create_webhook(ctx, CreateWebhook::new(MANAGED_WEBHOOK_NAME))
+        .await?;
+
+    Ok(webhook)
+}
+
+/// The key of a recipient of direct messages in the completed targets of a scheduled message
+pub fn recipient_key(user_id: UserId) -> String {
+    format!("dm:{user_id}")
+}
+
+/// Send the direct messages of a scheduled message in the background so the other due messages
+/// are not held up, then save it as completed. Does nothing if they are already being sent
+pub async fn spawn_direct_messages(
+    ctx: &Context,
+    content: MessageContent,
+    schedule_id: u32,
+    recipients: Vec<UserId>,
+) {
+    if !start_sending_direct_messages(ctx, schedule_id).await {
+        return;
+    }
+
+    let ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        let summary = send_direct_messages(&ctx, &content, schedule_id, &recipients).await;
+
+        info!(
+            "Scheduled message with id {schedule_id} was sent as direct message to {} of {} recipients. {} had their direct messages closed and {} failed for other reasons",
+            summary.sent,
+            recipients.len(),
+            summary.dms_closed,
+            summary.failed
+        );
+
+        save_as_completed(&ctx, &mut CompletedScheduled::default(), schedule_id).await;
+        stop_sending_direct_messages(&ctx, schedule_id).await;
+    });
+}
+
+/// Send the message to each recipient as a direct message, waiting between each recipient to stay
+/// clear of rate limits. Each delivered recipient is saved as completed right away so they are
+/// skipped if the bot stops midway. Every failed recipient is saved to dm_failed.json
+async fn send_direct_messages(
+    ctx: &Context,
+    content: &MessageContent,
+    schedule_id: u32,
+    recipients: &[UserId],
+) -> DirectMessageSummary {
+    let mut summary = DirectMessageSummary::default();
+    let mut completed = CompletedScheduled::default();
+    let mut failures = Vec::new();
+
+    for (index, user_id) in recipients.iter().enumerate() {
+        if index > 0 {
+            sleep(DM_INTERVAL).await;
+        }
+
+        let result = user_id.direct_message(ctx, content.to_message()).await;
+
+        if let Err(e) = result {
+            let dms_closed = is_dm_closed(&e);
+
+            if dms_closed {
+                summary.dms_closed += 1;
+            } else {
+                summary.failed += 1;
+            }
+
+            error!("Failed to send the scheduled message with id {schedule_id} as direct message to user {user_id}. Reason: {e}");
+
+            failures.push(FailedDirectMessage {
+                schedule_id,
+                user_id: *user_id,
+                dms_closed,
+                reason: e.to_string(),
+                failed_at: Utc::now(),
+            });
+        } else {
+            summary.sent += 1;
+            save_target_as_completed(ctx, &mut completed, schedule_id, recipient_key(*user_id))
+                .await;
+        }
+    }
+
+    if failures.is_empty() {
+        return summary;
+    }
+
+    let failed_data = FailedDirectMessages::get_failed_direct_messages();
+
+    if let Err(e) = &failed_data {
+        error!("Failed to read failed direct message data. Reason: {e}");
+        return summary;
+    }
+
+    let mut failed = failed_data.unwrap();
+
+    for failure in failures {
+        failed.add_new_failed(failure);
+    }
+
+    if let Err(e) = failed.save_failed_direct_messages() {
+        error!("Failed to save failed direct message data. Reason: {e}");
+    }
+
+    summary
+}
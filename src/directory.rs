@@ -0,0 +1,300 @@
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::utils::{get_all_guilds, get_target_channel};
+use crate::GuildDirectory;
+
+/// The channels, threads and roles of a guild the bot is in
+#[derive(Default)]
+struct GuildEntry {
+    name: String,
+    channels: HashMap<ChannelId, GuildChannel>,
+    threads: HashMap<ChannelId, GuildChannel>,
+    roles: HashMap<RoleId, Role>,
+    events: HashMap<ScheduledEventId, ScheduledEvent>,
+}
+
+/// In memory directory of the guilds, channels and roles of the bot. Built from the gateway
+/// events so looking up a guild or channel does not need to go over http
+#[derive(Default)]
+pub struct Directory {
+    guilds: HashMap<GuildId, GuildEntry>,
+}
+
+impl Directory {
+    /// Add a guild with all of its channels, threads and roles. Replaces the existing data
+    pub fn insert_guild(&mut self, guild: &Guild) {
+        let entry = GuildEntry {
+            name: guild.name.clone(),
+            channels: guild.channels.clone(),
+            threads: guild
+                .threads
+                .iter()
+                .map(|thread| (thread.id, thread.clone()))
+                .collect(),
+            roles: guild.roles.clone(),
+            events: guild
+                .scheduled_events
+                .iter()
+                .map(|event| (event.id, event.clone()))
+                .collect(),
+        };
+
+        self.guilds.insert(guild.id, entry);
+    }
+
+    /// Add a guild that is only known by its id and name, such as one found over http
+    pub fn insert_guild_name(&mut self, guild_id: GuildId, name: String) {
+        self.guilds.entry(guild_id).or_default().name = name;
+    }
+
+    pub fn remove_guild(&mut self, guild_id: GuildId) {
+        self.guilds.remove(&guild_id);
+    }
+
+    /// Add or replace a channel or a thread
+    pub fn upsert_channel(&mut self, channel: &GuildChannel) {
+        let entry = self.guilds.entry(channel.guild_id).or_default();
+
+        if channel.thread_metadata.is_some() {
+            entry.threads.insert(channel.id, channel.clone());
+        } else {
+            entry.channels.insert(channel.id, channel.clone());
+        }
+    }
+
+    /// Remove a channel or a thread
+    pub fn remove_channel(&mut self, guild_id: GuildId, channel_id: ChannelId) {
+        if let Some(entry) = self.guilds.get_mut(&guild_id) {
+            entry.channels.remove(&channel_id);
+            entry.threads.remove(&channel_id);
+        }
+    }
+
+    pub fn upsert_role(&mut self, role: &Role) {
+        let entry = self.guilds.entry(role.guild_id).or_default();
+        entry.roles.insert(role.id, role.clone());
+    }
+
+    pub fn remove_role(&mut self, guild_id: GuildId, role_id: RoleId) {
+        if let Some(entry) = self.guilds.get_mut(&guild_id) {
+            entry.roles.remove(&role_id);
+        }
+    }
+
+    /// Add or replace a guild scheduled event
+    pub fn upsert_event(&mut self, event: &ScheduledEvent) {
+        let entry = self.guilds.entry(event.guild_id).or_default();
+        entry.events.insert(event.id, event.clone());
+    }
+
+    pub fn remove_event(&mut self, guild_id: GuildId, event_id: ScheduledEventId) {
+        if let Some(entry) = self.guilds.get_mut(&guild_id) {
+            entry.events.remove(&event_id);
+        }
+    }
+
+    /// Find a guild scheduled event that was not cancelled by its id or name. The id is used if
+    /// both are given
+    pub fn find_event(
+        &self,
+        guild_id: GuildId,
+        event_id: Option<ScheduledEventId>,
+        event_name: Option<&str>,
+    ) -> Option<ScheduledEvent> {
+        self.guilds
+            .get(&guild_id)?
+            .events
+            .values()
+            .filter(|event| event.status != ScheduledEventStatus::Canceled)
+            .find(|event| {
+                if let Some(id) = event_id {
+                    event.id == id
+                } else {
+                    Some(event.name.as_str()) == event_name
+                }
+            })
+            .cloned()
+    }
+
+    /// Find a guild by its id or name. The id is used if both are given
+    pub fn find_guild(
+        &self,
+        guild_id: Option<GuildId>,
+        guild_name: Option<&str>,
+    ) -> Option<GuildId> {
+        if let Some(id) = guild_id {
+            return self.guilds.contains_key(&id).then_some(id);
+        }
+
+        self.guilds
+            .iter()
+            .find(|(_id, entry)| Some(entry.name.as_str()) == guild_name)
+            .map(|(id, _entry)| *id)
+    }
+
+    pub fn guild_name(&self, guild_id: GuildId) -> Option<String> {
+        self.guilds.get(&guild_id).map(|entry| entry.name.clone())
+    }
+
+    /// Find a channel or an active thread in the guild by its id or name. The id is used if both
+    /// are given
+    pub fn find_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+        channel_name: Option<&str>,
+    ) -> Option<GuildChannel> {
+        let entry = self.guilds.get(&guild_id)?;
+
+        entry
+            .channels
+            .values()
+            .chain(entry.threads.values())
+            .find(|channel| {
+                if let Some(id) = channel_id {
+                    channel.id == id
+                } else {
+                    Some(channel.name.as_str()) == channel_name
+                }
+            })
+            .cloned()
+    }
+
+    /// Get a cached channel or thread of the guild by its id
+    pub fn channel(&self, guild_id: GuildId, channel_id: ChannelId) -> Option<GuildChannel> {
+        let entry = self.guilds.get(&guild_id)?;
+
+        entry
+            .channels
+            .get(&channel_id)
+            .or_else(|| entry.threads.get(&channel_id))
+            .cloned()
+    }
+
+    pub fn find_role(&self, guild_id: GuildId, role_name: &str) -> Option<RoleId> {
+        self.guilds
+            .get(&guild_id)?
+            .roles
+            .values()
+            .find(|role| role.name == role_name)
+            .map(|role| role.id)
+    }
+}
+
+/// Get the shared guild directory
+pub async fn get_directory(ctx: &Context) -> Arc<RwLock<Directory>> {
+    let data_read = ctx.data.read().await;
+    data_read.get::<GuildDirectory>().unwrap().clone()
+}
+
+/// Find a guild by its id or name in the directory. If it is not there, the guild list is fetched
+/// over http and the directory is updated with it
+pub async fn resolve_guild(
+    ctx: &Context,
+    guild_id: Option<GuildId>,
+    guild_name: Option<&str>,
+) -> Result<Option<GuildId>, SerenityError> {
+    let directory = get_directory(ctx).await;
+
+    if let Some(id) = directory.read().await.find_guild(guild_id, guild_name) {
+        return Ok(Some(id));
+    }
+
+    let guilds = get_all_guilds(ctx).await?;
+    let mut directory = directory.write().await;
+
+    for guild in guilds {
+        directory.insert_guild_name(guild.id, guild.name);
+    }
+
+    Ok(directory.find_guild(guild_id, guild_name))
+}
+
+/// Find a channel or a thread in the guild by its id or name in the directory. If it is not there,
+/// the channel is searched over http and added to the directory when found
+pub async fn resolve_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+    channel_name: Option<&str>,
+    search_archived: bool,
+) -> Result<Option<GuildChannel>, SerenityError> {
+    let directory = get_directory(ctx).await;
+
+    if let Some(channel) = directory
+        .read()
+        .await
+        .find_channel(guild_id, channel_id, channel_name)
+    {
+        return Ok(Some(channel));
+    }
+
+    let channel =
+        get_target_channel(ctx, guild_id, channel_id, channel_name, search_archived).await?;
+
+    if let Some((_id, channel)) = &channel {
+        info!(
+            "Channel {} was not in the directory. Adding it",
+            channel.name
+        );
+        directory.write().await.upsert_channel(channel);
+    }
+
+    Ok(channel.map(|(_id, channel)| channel))
+}
+
+/// Find a role in the guild by its name in the directory. If it is not there, the roles of the
+/// guild are fetched over http and the directory is updated with them
+pub async fn resolve_role(
+    ctx: &Context,
+    guild_id: GuildId,
+    role_name: &str,
+) -> Result<Option<RoleId>, SerenityError> {
+    let directory = get_directory(ctx).await;
+
+    if let Some(role_id) = directory.read().await.find_role(guild_id, role_name) {
+        return Ok(Some(role_id));
+    }
+
+    let roles = guild_id.roles(ctx).await?;
+    let mut directory = directory.write().await;
+
+    for role in roles.values() {
+        directory.upsert_role(role);
+    }
+
+    Ok(directory.find_role(guild_id, role_name))
+}
+
+/// Find a guild scheduled event by its id or name in the directory. If it is not there, the events
+/// of the guild are fetched over http and the directory is updated with them
+pub async fn resolve_event(
+    ctx: &Context,
+    guild_id: GuildId,
+    event_id: Option<ScheduledEventId>,
+    event_name: Option<&str>,
+) -> Result<Option<ScheduledEvent>, SerenityError> {
+    let directory = get_directory(ctx).await;
+
+    if let Some(event) = directory
+        .read()
+        .await
+        .find_event(guild_id, event_id, event_name)
+    {
+        return Ok(Some(event));
+    }
+
+    let events = guild_id.scheduled_events(ctx, false).await?;
+    let mut directory = directory.write().await;
+
+    for event in &events {
+        directory.upsert_event(event);
+    }
+
+    Ok(directory.find_event(guild_id, event_id, event_name))
+}
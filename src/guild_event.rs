@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Error};
+use chrono::Utc;
+use serenity::builder::{CreateAttachment, CreateScheduledEvent};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use tracing::{error, info};
+
+use crate::config::{CreatedEvent, CreatedEvents, EventData};
+use crate::directory::{resolve_channel, resolve_guild};
+use crate::utils::display_target;
+
+/// Create every guild scheduled event in events.json whose creation time has been reached and was
+/// not created yet. Events without a guild are created in the global target guild
+pub async fn create_due_events(ctx: &Context, target_guild: GuildId, search_archived: bool) {
+    let events_data = EventData::get_all_events();
+
+    if let Err(e) = &events_data {
+        error!("Failed to read event data. Reason: {e}");
+        return;
+    }
+
+    let created_data = CreatedEvents::get_created_events();
+
+    if let Err(e) = &created_data {
+        error!("Failed to read created event data. Reason: {e}");
+        return;
+    }
+
+    let events = events_data.unwrap();
+    let mut created = created_data.unwrap();
+    let now = Utc::now();
+
+    for event in events {
+        if created.get_created(event.id()).is_some() || now < event.create_at() {
+            continue;
+        }
+
+        let result = create_event(ctx, &event, target_guild, search_archived).await;
+
+        if let Err(e) = result {
+            error!(
+                "Failed to create the event with id {}. This will be tried again. Reason: {e}",
+                event.id()
+            );
+            continue;
+        }
+
+        let guild_event = result.unwrap();
+
+        info!(
+            "Event with id {} was created as {}",
+            event.id(),
+            guild_event.name
+        );
+
+        created.add_new_created(CreatedEvent {
+            event_id: event.id(),
+            guild_id: guild_event.guild_id,
+            scheduled_event_id: guild_event.id,
+            created_at: now,
+        });
+
+        // The event would be created again if this is not saved so stop creating any more
+        if let Err(e) = created.save_created_events() {
+            error!("Failed to save created event data. Reason: {e}");
+            return;
+        }
+    }
+}
+
+/// Create a guild scheduled event from the event data. A voice or stage channel creates an event
+/// in that channel, otherwise the location is used
+async fn create_event(
+    ctx: &Context,
+    event: &EventData,
+    target_guild: GuildId,
+    search_archived: bool,
+) -> Result<ScheduledEvent, Error> {
+    if event.has_voice_channel() && event.location.is_some() {
+        return Err(anyhow!(
+            "location cannot be used along with voice_channel or voice_channel_id"
+        ));
+    }
+
+    if !event.has_voice_channel() && event.location.is_none() {
+        return Err(anyhow!("Either location or voice_channel must be provided"));
+    }
+
+    let mut guild_id = target_guild;
+
+    if event.has_target_guild() {
+        guild_id = resolve_guild(ctx, event.target_guild_id, event.target_guild.as_deref())
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "The {} guild was not found",
+                    display_target(event.target_guild_id, &event.target_guild)
+                )
+            })?;
+    }
+
+    let mut builder = if let Some(location) = &event.location {
+        let end_at = event
+            .end_at()
+            .ok_or_else(|| anyhow!("end_at is required for an event with a location"))?;
+
+        CreateScheduledEvent::new(ScheduledEventType::External, event.name(), event.start_at())
+            .location(location)
+            .end_time(end_at)
+    } else {
+        let channel = resolve_channel(
+            ctx,
+            guild_id,
+            event.voice_channel_id,
+            event.voice_channel.as_deref(),
+            search_archived,
+        )
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "The {} channel was not found",
+                display_target(event.voice_channel_id, &event.voice_channel)
+            )
+        })?;
+
+        let kind = match channel.kind {
+            ChannelType::Voice => ScheduledEventType::Voice,
+            ChannelType::Stage => ScheduledEventType::StageInstance,
+            _ => return Err(anyhow!("{} is not a voice or stage channel", channel.name)),
+        };
+
+        let mut builder =
+            CreateScheduledEvent::new(kind, event.name(), event.start_at()).channel_id(channel.id);
+
+        if let Some(end_at) = event.end_at() {
+            builder = builder.end_time(end_at);
+        }
+
+        builder
+    };
+
+    if let Some(description) = event.description() {
+        builder = builder.description(description);
+    }
+
+    let image = if let Some(location) = &event.image {
+        Some(CreateAttachment::path(location).await?)
+    } else {
+        None
+    };
+
+    if let Some(image) = &image {
+        builder = builder.image(image);
+    }
+
+    let guild_event = guild_id.create_scheduled_event(ctx, builder).await?;
+
+    Ok(guild_event)
+}
@@ -1,14 +1,26 @@
 pub mod bot;
+mod check;
+pub mod commands;
 pub mod config;
+pub mod delivery;
+pub mod directory;
+pub mod guild_event;
+pub mod migrate;
 pub mod utils;
 
 use bot::Handler;
-use config::{BotConfig, QuizData};
+use check::check_config;
+use commands::compose::ComposeDraft;
+use config::{AdminData, BotConfig, QuizData};
+use directory::Directory;
+use migrate::migrate_to_ids;
+use serenity::http::Http;
 use serenity::model::prelude::*;
 use serenity::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use tracing::error;
 
 /// Saves a quiz data to track whether a quiz is ongoing
@@ -18,6 +30,51 @@ impl TypeMapKey for OngoingQuiz {
     type Value = Arc<Mutex<Option<QuizData>>>;
 }
 
+/// The guilds, channels and roles known to the bot
+pub struct GuildDirectory;
+
+impl TypeMapKey for GuildDirectory {
+    type Value = Arc<RwLock<Directory>>;
+}
+
+/// Who can use the commands of the bot, from the bot config
+pub struct BotAdmins;
+
+impl TypeMapKey for BotAdmins {
+    type Value = Arc<Option<Vec<AdminData>>>;
+}
+
+/// Scheduled messages composed with a modal that are waiting to be confirmed, by the id of the
+/// modal interaction
+pub struct ComposeDrafts;
+
+impl TypeMapKey for ComposeDrafts {
+    type Value = Arc<Mutex<HashMap<InteractionId, ComposeDraft>>>;
+}
+
+/// Held while schedule.json is read, changed and written back so two commands cannot take the same
+/// id or overwrite each other
+pub struct ScheduleLock;
+
+impl TypeMapKey for ScheduleLock {
+    type Value = Arc<Mutex<()>>;
+}
+
+/// Held while completed.json is read, changed and written back so the direct messages sent in the
+/// background and the scheduling loop cannot overwrite each other
+pub struct CompletedLock;
+
+impl TypeMapKey for CompletedLock {
+    type Value = Arc<Mutex<()>>;
+}
+
+/// The ids of the scheduled messages whose direct messages are being sent in the background
+pub struct SendingDirectMessages;
+
+impl TypeMapKey for SendingDirectMessages {
+    type Value = Arc<Mutex<HashSet<u32>>>;
+}
+
 pub struct ThreadStarted;
 
 impl TypeMapKey for ThreadStarted {
@@ -27,6 +84,15 @@ impl TypeMapKey for ThreadStarted {
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
+
+    // Validate the config files without connecting to discord, such as before a deploy
+    if std::env::args().nth(1).as_deref() == Some("check") {
+        if check_config() > 0 {
+            std::process::exit(1)
+        }
+        return;
+    }
+
     let bot_config = BotConfig::get_config();
 
     if let Err(e) = bot_config {
@@ -37,6 +103,19 @@ async fn main() {
     let config = bot_config.unwrap();
 
     let token = config.get_token();
+    let admins = config.admins();
+
+    // One time migration of the guild and channel names in the config files into ids
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        let http = Http::new(&token);
+
+        if let Err(e) = migrate_to_ids(&http).await {
+            error!("Failed to migrate the config files. Error: {e}");
+            std::process::exit(1)
+        }
+        return;
+    }
+
     let intent = GatewayIntents::default().union(GatewayIntents::MESSAGE_CONTENT);
 
     let mut client = Client::builder(token, intent)
@@ -54,6 +133,36 @@ async fn main() {
         data.insert::<ThreadStarted>(AtomicBool::new(false));
     }
 
+    {
+        let mut data = client.data.write().await;
+        data.insert::<GuildDirectory>(Arc::new(RwLock::new(Directory::default())));
+    }
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<BotAdmins>(Arc::new(admins));
+    }
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<ComposeDrafts>(Arc::new(Mutex::new(HashMap::new())));
+    }
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<ScheduleLock>(Arc::new(Mutex::new(())));
+    }
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<CompletedLock>(Arc::new(Mutex::new(())));
+    }
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<SendingDirectMessages>(Arc::new(Mutex::new(HashSet::new())));
+    }
+
     if let Err(e) = client.start().await {
         error!("Client error: {e}");
     }
@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Context, Error};
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::{Map, Serializer, Value};
+use serenity::http::Http;
+use serenity::model::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use tracing::{error, info};
+
+use crate::config::{ScheduledMessage, TargetData};
+use crate::utils::get_all_guilds;
+
+/// Resolves guild and channel names into ids using the live guild data of the bot
+struct Resolver<'a> {
+    http: &'a Http,
+    guilds: Vec<GuildInfo>,
+    channels: HashMap<GuildId, HashMap<ChannelId, GuildChannel>>,
+}
+
+impl<'a> Resolver<'a> {
+    async fn new(http: &'a Http) -> Result<Self, Error> {
+        let guilds = get_all_guilds(http).await?;
+
+        Ok(Self {
+            http,
+            guilds,
+            channels: HashMap::new(),
+        })
+    }
+
+    fn guild_id(&self, name: &str) -> Option<GuildId> {
+        self.guilds
+            .iter()
+            .find(|guild| guild.name == name)
+            .map(|guild| guild.id)
+    }
+
+    async fn channel_id(
+        &mut self,
+        guild_id: GuildId,
+        name: &str,
+    ) -> Result<Option<ChannelId>, Error> {
+        if !self.channels.contains_key(&guild_id) {
+            let channels = guild_id.channels(self.http).await?;
+            self.channels.insert(guild_id, channels);
+        }
+
+        let channel_id = self.channels[&guild_id]
+            .iter()
+            .find(|(_id, channel)| channel.name == name)
+            .map(|(id, _channel)| *id);
+
+        Ok(channel_id)
+    }
+
+    /// Replace the guild and channel names under the given keys of a config object with their ids
+    /// under the same key with an `_id` suffix. Channels are searched in the guild of the object
+    /// or in the default guild if it has none. Returns the number of replaced names
+    async fn migrate_object(
+        &mut self,
+        object: &mut Map<String, Value>,
+        guild_key: &str,
+        channel_key: &str,
+        default_guild: Option<GuildId>,
+    ) -> Result<usize, Error> {
+        let mut replaced = 0;
+        let guild_id_key = format!("{guild_key}_id");
+        let channel_id_key = format!("{channel_key}_id");
+
+        let mut guild_id = object
+            .get(&guild_id_key)
+            .and_then(|id| serde_json::from_value::<GuildId>(id.clone()).ok());
+
+        if let Some(Value::String(name)) = object.get(guild_key) {
+            let name = name.clone();
+            if let Some(id) = self.guild_id(&name) {
+                replace_key(object, guild_key, guild_id_key, id.to_string());
+                guild_id = Some(id);
+                replaced += 1;
+            } else {
+                error!("Guild {name} was not found. It will be kept as is");
+                return Ok(replaced);
+            }
+        }
+
+        let guild_id = guild_id.or(default_guild);
+
+        if guild_id.is_none() {
+            return Ok(replaced);
+        }
+
+        let guild_id = guild_id.unwrap();
+
+        if let Some(Value::String(name)) = object.get(channel_key) {
+            let name = name.clone();
+            if let Some(id) = self.channel_id(guild_id, &name).await? {
+                replace_key(object, channel_key, channel_id_key, id.to_string());
+                replaced += 1;
+            } else {
+                error!("Channel {name} was not found. It will be kept as is");
+            }
+        }
+
+        Ok(replaced)
+    }
+}
+
+/// Replace a key of a config object with a new key and value, keeping the position of the key
+fn replace_key(object: &mut Map<String, Value>, key: &str, new_key: String, value: String) {
+    let index = object.keys().position(|k| k == key).unwrap_or(object.len());
+    object.shift_remove(key);
+    object.shift_insert(index, new_key, Value::String(value));
+}
+
+fn read_config(name: &str) -> Result<Value, Error> {
+    let json_string =
+        fs::read_to_string(format!("config/{name}")).context(format!("Failed to read {name}"))?;
+    let value = serde_json::from_str(&json_string).context(format!("Failed to parse {name}"))?;
+    Ok(value)
+}
+
+/// Write the config file with the same indentation as the example configs. A backup of the
+/// original file is kept with a `.bak` extension
+fn write_config(name: &str, value: &Value) -> Result<(), Error> {
+    let path = format!("config/{name}");
+    fs::copy(&path, format!("{path}.bak")).context(format!("Failed to back up {name}"))?;
+
+    let mut data = Vec::new();
+    let mut serializer =
+        Serializer::with_formatter(&mut data, PrettyFormatter::with_indent(b"    "));
+    value
+        .serialize(&mut serializer)
+        .context("Failed to serialize data")?;
+    data.push(b'\n');
+
+    fs::write(&path, data).context(format!("Failed to write to {name}"))?;
+    Ok(())
+}
+
+/// The id of a scheduled message in schedule.json and the keys its targets are tracked by in
+/// completed.json
+fn target_keys(object: &Map<String, Value>) -> Option<(u32, Vec<String>)> {
+    let message = serde_json::from_value::<ScheduledMessage>(Value::Object(object.clone())).ok()?;
+    let keys = message.get_targets().iter().map(TargetData::key).collect();
+
+    Some((message.id(), keys))
+}
+
+/// Rename the completed targets in completed.json whose guild or channel name was replaced with
+/// an id, so targets that already got the message are not sent it again
+fn migrate_completed_targets(renamed_targets: &[(u32, String, String)]) -> Result<(), Error> {
+    if renamed_targets.is_empty() {
+        return Ok(());
+    }
+
+    let mut completed = read_config("completed.json")?;
+    let mut replaced = 0;
+
+    let completed_targets = completed
+        .get_mut("completed_targets")
+        .and_then(Value::as_object_mut);
+
+    if let Some(completed_targets) = completed_targets {
+        for (id, old_key, new_key) in renamed_targets {
+            let targets = completed_targets
+                .get_mut(&id.to_string())
+                .and_then(Value::as_array_mut);
+
+            for target in targets.into_iter().flatten() {
+                if target.as_str() == Some(old_key.as_str()) {
+                    *target = Value::String(new_key.clone());
+                    replaced += 1;
+                }
+            }
+        }
+    }
+
+    if replaced > 0 {
+        write_config("completed.json", &completed)?;
+    }
+    info!("Replaced {replaced} completed targets in completed.json");
+
+    Ok(())
+}
+
+/// Rewrite every guild and channel name in bot_config.json, schedule.json and quiz.json into ids
+/// using the live guild data, so renaming a guild or channel no longer breaks the config. The
+/// completed targets in completed.json are renamed along with them
+pub async fn migrate_to_ids(http: &Http) -> Result<(), Error> {
+    let mut resolver = Resolver::new(http).await?;
+
+    let mut bot_config = read_config("bot_config.json")?;
+    let config_object = bot_config
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("bot_config.json must be an object"))?;
+
+    let replaced = resolver
+        .migrate_object(config_object, "target_guild", "target_channel", None)
+        .await?;
+
+    let default_guild = config_object
+        .get("target_guild_id")
+        .and_then(|id| serde_json::from_value::<GuildId>(id.clone()).ok());
+
+    if replaced > 0 {
+        write_config("bot_config.json", &bot_config)?;
+    }
+    info!("Replaced {replaced} names with ids in bot_config.json");
+
+    let mut schedule = read_config("schedule.json")?;
+    let mut replaced = 0;
+    let mut renamed_targets = Vec::new();
+
+    for object in schedule
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        let old_keys = target_keys(object);
+
+        replaced += resolver
+            .migrate_object(object, "target_guild", "target_channel", default_guild)
+            .await?;
+
+        if let Some(Value::Array(targets)) = object.get_mut("targets") {
+            for target in targets.iter_mut().filter_map(Value::as_object_mut) {
+                replaced += resolver
+                    .migrate_object(target, "guild", "channel", default_guild)
+                    .await?;
+            }
+        }
+
+        if let Some((id, old_keys)) = old_keys {
+            if let Some((_id, new_keys)) = target_keys(object) {
+                for (old_key, new_key) in old_keys.into_iter().zip(new_keys) {
+                    if old_key != new_key {
+                        renamed_targets.push((id, old_key, new_key));
+                    }
+                }
+            }
+        }
+    }
+
+    if replaced > 0 {
+        write_config("schedule.json", &schedule)?;
+    }
+    info!("Replaced {replaced} names with ids in schedule.json");
+
+    migrate_completed_targets(&renamed_targets)?;
+
+    let mut quizzes = read_config("quiz.json")?;
+    let mut replaced = 0;
+
+    for quiz in quizzes
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        replaced += resolver
+            .migrate_object(quiz, "monitor_guild", "monitor_channel", default_guild)
+            .await?;
+    }
+
+    if replaced > 0 {
+        write_config("quiz.json", &quizzes)?;
+    }
+    info!("Replaced {replaced} names with ids in quiz.json");
+
+    Ok(())
+}
@@ -1,61 +1,216 @@
 use anyhow::{anyhow, Error};
-use chrono::{Timelike, Utc};
-use serenity::builder::{CreateAttachment, CreateMessage, CreatePoll, CreatePollAnswer};
+use chrono::{DateTime, TimeDelta, Timelike, Utc};
+use serenity::builder::create_poll::Ready;
+use serenity::builder::{
+    CreateAllowedMentions, CreateAttachment, CreatePoll, CreatePollAnswer, CreateThread, EditThread,
+};
+use serenity::http::{GuildPagination, Http, HttpError, StatusCode};
 use serenity::model::prelude::*;
 use serenity::prelude::*;
+use serenity::utils::parse_message_url;
+use std::collections::HashSet;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time;
 use tokio::time::{sleep, Duration};
-use tracing::error;
+use tracing::{error, info};
+
+use crate::config::{
+    AllowedMentionsData, CompletedScheduled, PinnedMessage, PinnedMessages, PollData, QuizData,
+    ScheduledMessage, SentMessage, SentMessages,
+};
+use crate::directory::{get_directory, resolve_role};
+use crate::{CompletedLock, OngoingQuiz, SendingDirectMessages, ThreadStarted};
+
+pub const MAX_POLL_MINUTES: u64 = 10_080;
+pub const MAX_THREAD_NAME_LENGTH: usize = 100;
+pub const MAX_SLOWMODE_SECONDS: u16 = 21_600;
+pub const ARCHIVE_DURATIONS: [u16; 4] = [60, 1440, 4320, 10_080];
+const MEMBER_PAGE_LIMIT: u64 = 1000;
+const GUILD_PAGE_LIMIT: u64 = 200;
+const ARCHIVED_THREAD_LIMIT: u64 = 100;
+const DMS_CLOSED_CODE: isize = 50007;
+
+/// Get every guild the bot is in over http, going through all pages of the guild list
+pub async fn get_all_guilds(http: impl AsRef<Http>) -> Result<Vec<GuildInfo>, SerenityError> {
+    let mut guilds = Vec::new();
+    let mut after = None;
+
+    loop {
+        let page = http
+            .as_ref()
+            .get_guilds(after.map(GuildPagination::After), Some(GUILD_PAGE_LIMIT))
+            .await?;
+
+        let page_size = page.len() as u64;
+        after = page.last().map(|guild| guild.id);
+        guilds.extend(page);
+
+        if page_size < GUILD_PAGE_LIMIT {
+            break;
+        }
+    }
 
-use crate::config::{PollData, QuizData};
-use crate::{OngoingQuiz, ThreadStarted};
+    Ok(guilds)
+}
+
+/// Try to find the target channel in the given guild over http by its id or name. The id is used
+/// if both are given. Active threads are searched if no channel matches. If `search_archived` is
+/// true, the archived public threads of every channel the bot can read are searched last and
+/// unarchived when found. Returns `None` if the guild has no such channel
+pub async fn get_target_channel(
+    http: impl AsRef<Http>,
+    guild_id: GuildId,
+    channel_id: Option<ChannelId>,
+    channel_name: Option<&str>,
+    search_archived: bool,
+) -> Result<Option<(ChannelId, GuildChannel)>, SerenityError> {
+    let is_target = |id: ChannelId, channel: &GuildChannel| {
+        if let Some(target_id) = channel_id {
+            id == target_id
+        } else {
+            Some(channel.name()) == channel_name
+        }
+    };
+
+    let channels = guild_id.channels(&http).await?;
+
+    for (id, channel) in &channels {
+        if is_target(*id, channel) {
+            return Ok(Some((*id, channel.clone())));
+        }
+    }
 
-const MAX_POLL_MINUTES: u64 = 10_080;
+    let active_threads = guild_id.get_active_threads(&http).await?;
 
-/// Try to find the target Guild in the bot guild list over http
-pub async fn get_target_guild(ctx: &Context, target_guild: &str) -> Option<GuildInfo> {
-    let guild_list = ctx.http().get_guilds(None, Some(100)).await;
+    for thread in active_threads.threads {
+        if is_target(thread.id, &thread) {
+            return Ok(Some((thread.id, thread)));
+        }
+    }
 
-    if guild_list.is_err() {
-        return None;
+    if !search_archived {
+        return Ok(None);
     }
 
-    let guilds = guild_list.unwrap();
+    for channel in channels.values() {
+        if !matches!(
+            channel.kind,
+            ChannelType::Text | ChannelType::News | ChannelType::Forum
+        ) {
+            continue;
+        }
 
-    for guild in guilds {
-        if guild.name == target_guild {
-            return Some(guild);
+        // Archived threads come newest first, one page at a time
+        let mut before = None;
+
+        loop {
+            let archived_threads = match channel
+                .id
+                .get_archived_public_threads(&http, before, Some(ARCHIVED_THREAD_LIMIT))
+                .await
+            {
+                Ok(archived_threads) => archived_threads,
+                // The bot cannot read every channel of the guild, so those are skipped
+                Err(e) if is_forbidden(&e) || is_not_found(&e) => break,
+                Err(e) => return Err(e),
+            };
+
+            before = archived_threads
+                .threads
+                .last()
+                .and_then(|thread| thread.thread_metadata)
+                .and_then(|metadata| metadata.archive_timestamp)
+                .and_then(|timestamp| u64::try_from(timestamp.unix_timestamp()).ok());
+
+            for thread in archived_threads.threads {
+                if is_target(thread.id, &thread) {
+                    let thread = thread
+                        .id
+                        .edit_thread(http.as_ref(), EditThread::new().archived(false))
+                        .await?;
+                    info!("Unarchived the thread {}", thread.name);
+                    return Ok(Some((thread.id, thread)));
+                }
+            }
+
+            if !archived_threads.has_more || before.is_none() {
+                break;
+            }
         }
     }
-    None
+
+    Ok(None)
 }
 
-/// Try to find the target channel in the given guild over http
-pub async fn get_target_channel(
-    ctx: &Context,
-    guild: &GuildInfo,
-    channel_name: &str,
-) -> Option<(ChannelId, GuildChannel)> {
-    let channel_list = guild.id.channels(ctx).await;
+/// Whether the message was sent in a thread of the given parent channel. Checks the directory
+/// first and only goes over http if the channel of the message is not there
+pub async fn in_thread_of(ctx: &Context, message: &Message, parent_id: ChannelId) -> bool {
+    if message.guild_id.is_none() {
+        return false;
+    }
 
-    if channel_list.is_err() {
-        return None;
+    let directory = get_directory(ctx).await;
+
+    if let Some(channel) = directory
+        .read()
+        .await
+        .channel(message.guild_id.unwrap(), message.channel_id)
+    {
+        return channel.thread_metadata.is_some() && channel.parent_id == Some(parent_id);
+    }
+
+    if let Ok(Channel::Guild(channel)) = message.channel_id.to_channel(ctx).await {
+        directory.write().await.upsert_channel(&channel);
+        return channel.thread_metadata.is_some() && channel.parent_id == Some(parent_id);
     }
 
-    let channels = channel_list.unwrap();
-    for (channel_id, channel) in channels {
-        if channel.name() == channel_name {
-            return Some((channel_id, channel));
+    false
+}
+
+/// Get every user that should receive the scheduled message as direct message. Members of the
+/// role are searched in the given guild. Bots are never included
+pub async fn get_dm_recipients(
+    ctx: &Context,
+    guild_id: GuildId,
+    scheduled: &ScheduledMessage,
+) -> Result<Vec<UserId>, Error> {
+    let mut recipients = scheduled.dm_users.clone().unwrap_or_default();
+
+    if let Some(role_name) = &scheduled.dm_role {
+        let role_id = resolve_role(ctx, guild_id, role_name)
+            .await?
+            .ok_or_else(|| anyhow!("Role {role_name} was not found in the guild"))?;
+
+        // Members are fetched in pages in order of their user id
+        let mut after = None;
+        loop {
+            let members = guild_id
+                .members(ctx, Some(MEMBER_PAGE_LIMIT), after)
+                .await?;
+
+            for member in &members {
+                if !member.user.bot && member.roles.contains(&role_id) {
+                    recipients.push(member.user.id);
+                }
+            }
+
+            if (members.len() as u64) < MEMBER_PAGE_LIMIT {
+                break;
+            }
+            after = members.last().map(|member| member.user.id);
         }
     }
-    None
+
+    let mut seen = HashSet::new();
+    recipients.retain(|user_id| seen.insert(*user_id));
+
+    Ok(recipients)
 }
 
-/// Add a poll to a discord message
-pub fn add_poll(message: CreateMessage, id: u32) -> Result<CreateMessage, Error> {
+/// Create a discord poll from the poll data with the given id
+pub fn create_poll(id: u32) -> Result<CreatePoll<Ready>, Error> {
     let poll_data = PollData::get_poll_data(id)?;
 
     let mut poll_answers = Vec::new();
@@ -86,21 +241,412 @@ pub fn add_poll(message: CreateMessage, id: u32) -> Result<CreateMessage, Error>
         poll = poll.allow_multiselect()
     }
 
-    Ok(message.poll(poll))
+    Ok(poll)
 }
 
-/// Add a file path as attachments to a discord message
-pub async fn add_attachments(
-    mut message: CreateMessage,
-    locations: &Vec<String>,
-) -> Result<CreateMessage, Error> {
+/// Read the files in the given paths as discord attachments
+pub async fn create_attachments(locations: &Vec<String>) -> Result<Vec<CreateAttachment>, Error> {
+    let mut attachments = Vec::new();
+
     for location in locations {
         let attachment_path = Path::new(location);
         let attachment = CreateAttachment::path(attachment_path).await?;
-        message = message.add_file(attachment);
+        attachments.push(attachment);
+    }
+
+    Ok(attachments)
+}
+
+/// Build the allowed mentions of a message sent in the given guild. Roles are found by their name
+/// in the guild
+pub async fn create_allowed_mentions(
+    ctx: &Context,
+    guild_id: GuildId,
+    data: &AllowedMentionsData,
+) -> Result<CreateAllowedMentions, Error> {
+    let mut allowed_mentions = CreateAllowedMentions::new()
+        .everyone(data.everyone())
+        .all_users(data.users());
+
+    // Discord rejects a role list along with all roles being allowed
+    if data.all_roles() {
+        return Ok(allowed_mentions.all_roles(true));
+    }
+
+    let mut role_ids = Vec::new();
+
+    for role_name in data.roles() {
+        let role_id = resolve_role(ctx, guild_id, &role_name)
+            .await?
+            .ok_or_else(|| anyhow!("Role {role_name} was not found in the guild"))?;
+        role_ids.push(role_id);
     }
 
-    Ok(message)
+    if !role_ids.is_empty() {
+        allowed_mentions = allowed_mentions.roles(role_ids);
+    }
+
+    Ok(allowed_mentions)
+}
+
+/// The message the scheduled message replies to in the given channel, from a message link or id
+/// or from the message sent by an earlier scheduled message. The reply is sent as a normal
+/// message if the referenced message was deleted
+pub fn get_reply_reference(
+    channel_id: ChannelId,
+    scheduled: &ScheduledMessage,
+) -> Result<Option<MessageReference>, Error> {
+    let message_id = if let Some(reply_to) = &scheduled.reply_to {
+        if let Some((_guild_id, link_channel_id, message_id)) = parse_message_url(reply_to) {
+            if link_channel_id != channel_id {
+                return Err(anyhow!(
+                    "Message {reply_to} is not in the channel the message is sent to"
+                ));
+            }
+            message_id
+        } else {
+            reply_to
+                .parse::<MessageId>()
+                .map_err(|_| anyhow!("{reply_to} is not a valid message link or id"))?
+        }
+    } else if let Some(schedule_id) = scheduled.reply_to_schedule {
+        SentMessages::get_sent_messages()?
+            .sent_in_channel(schedule_id, channel_id)
+            .map(|sent| sent.message_id)
+            .ok_or_else(|| {
+                anyhow!("No message sent by the scheduled message with id {schedule_id} was found in the channel")
+            })?
+    } else {
+        return Ok(None);
+    };
+
+    let reference = MessageReference::new(MessageReferenceKind::Default, channel_id)
+        .message_id(message_id)
+        .fail_if_not_exists(false);
+
+    Ok(Some(reference))
+}
+
+/// Save the sent message of a scheduled message so later scheduled messages can reply to it
+pub fn save_sent_message(message: &Message, schedule_id: u32) -> Result<(), Error> {
+    let mut sent = SentMessages::get_sent_messages()?;
+
+    sent.add_new_sent(SentMessage {
+        schedule_id,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        sent_at: Utc::now(),
+    });
+
+    sent.save_sent_messages()
+}
+
+/// Add the reactions to the sent message in the given order. Custom emojis are given by their
+/// name and searched in the guild. Failed reactions are logged and skipped
+pub async fn add_reactions(
+    ctx: &Context,
+    message: &Message,
+    guild_id: GuildId,
+    reactions: &[String],
+    schedule_id: u32,
+) {
+    let mut guild_emojis = None;
+
+    for reaction in reactions {
+        let name = reaction.trim_matches(':');
+
+        // Unicode emojis and the full custom emoji format are used as is, anything else is the
+        // name of a custom emoji
+        let is_name =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        let reaction_type = if is_name {
+            if guild_emojis.is_none() {
+                match guild_id.emojis(ctx).await {
+                    Ok(emojis) => guild_emojis = Some(emojis),
+                    Err(e) => {
+                        error!("Failed to fetch the emojis of the guild for the scheduled message with id {schedule_id}. Reason: {e}");
+                        guild_emojis = Some(Vec::new());
+                    }
+                }
+            }
+
+            let emoji = guild_emojis
+                .iter()
+                .flatten()
+                .find(|emoji| emoji.name == name);
+
+            if let Some(emoji) = emoji {
+                ReactionType::from(emoji.clone())
+            } else {
+                error!("Emoji {reaction} was not found in the guild for the scheduled message with id {schedule_id}. The reaction will be skipped.");
+                continue;
+            }
+        } else {
+            match ReactionType::try_from(reaction.as_str()) {
+                Ok(reaction_type) => reaction_type,
+                Err(e) => {
+                    error!("Invalid reaction {reaction} for the scheduled message with id {schedule_id}. The reaction will be skipped. Reason: {e}");
+                    continue;
+                }
+            }
+        };
+
+        if let Err(e) = message.react(ctx, reaction_type).await {
+            error!("Failed to add reaction {reaction} to the scheduled message with id {schedule_id}. Reason: {e}");
+        }
+    }
+}
+
+/// A readable form of a guild or channel given by its id or name for logging
+pub fn display_target(id: Option<impl std::fmt::Display>, name: &Option<String>) -> String {
+    if let Some(id) = id {
+        id.to_string()
+    } else {
+        name.clone().unwrap_or_default()
+    }
+}
+
+/// Start a thread from a sent scheduled message using the thread data of the scheduled message
+pub async fn start_thread(
+    ctx: &Context,
+    message: &Message,
+    scheduled: &ScheduledMessage,
+) -> Result<GuildChannel, Error> {
+    let thread_data = scheduled
+        .thread
+        .as_ref()
+        .ok_or_else(|| anyhow!("No thread data found"))?;
+
+    let name = thread_data
+        .name(
+            scheduled.id(),
+            scheduled.scheduled_at().unwrap_or_else(Utc::now),
+        )
+        .chars()
+        .take(MAX_THREAD_NAME_LENGTH)
+        .collect::<String>();
+
+    if name.trim().is_empty() {
+        return Err(anyhow!("Thread name cannot be empty"));
+    }
+
+    let archive_minutes = thread_data.auto_archive_minutes();
+
+    if !ARCHIVE_DURATIONS.contains(&archive_minutes) {
+        return Err(anyhow!(
+            "Thread auto archive duration must be one of 60, 1440, 4320 or 10080 minutes"
+        ));
+    }
+
+    let mut slowmode = thread_data.slowmode_seconds();
+
+    if slowmode > MAX_SLOWMODE_SECONDS {
+        error!(
+            "Thread slowmode is larger than the maximum allowed time. Setting to 21,600 seconds"
+        );
+        slowmode = MAX_SLOWMODE_SECONDS;
+    }
+
+    let thread = CreateThread::new(name)
+        .auto_archive_duration(AutoArchiveDuration::from(archive_minutes))
+        .rate_limit_per_user(slowmode);
+
+    let channel = message
+        .channel_id
+        .create_thread_from_message(ctx, message.id, thread)
+        .await?;
+
+    Ok(channel)
+}
+
+/// Pin a sent scheduled message and keep track of it as pinned by the bot. If `unpin_oldest_at`
+/// is provided and the channel has reached that many pins, the oldest message pinned by the bot
+/// in the channel is unpinned first. Failing to read or save pinned.json does not stop the message
+/// from being pinned, it is only not tracked
+pub async fn pin_scheduled_message(
+    ctx: &Context,
+    message: &Message,
+    schedule_id: u32,
+    unpin_after: Option<u64>,
+    unpin_oldest_at: Option<usize>,
+) -> Result<(), Error> {
+    let pinned_data = PinnedMessages::get_pinned_messages();
+
+    if let Err(e) = &pinned_data {
+        error!("Failed to read pinned message data. The message from the scheduled message with id {schedule_id} will be pinned without being tracked. Reason: {e}");
+    }
+
+    let mut pinned = pinned_data.ok();
+
+    if let (Some(limit), Some(pinned)) = (unpin_oldest_at, pinned.as_mut()) {
+        let pin_count = message.channel_id.pins(ctx).await?.len();
+
+        if pin_count >= limit {
+            if let Some(oldest) = pinned.oldest_in_channel(message.channel_id) {
+                info!(
+                    "Pin limit reached. Unpinning the message from the scheduled message with id {}",
+                    oldest.schedule_id
+                );
+                unpin_tracked(ctx, pinned, &oldest).await;
+            } else {
+                error!("Pin limit reached but no message pinned by the bot was found in the channel to unpin");
+            }
+        }
+    }
+
+    message.pin(ctx).await?;
+
+    if pinned.is_none() {
+        return Ok(());
+    }
+
+    let mut pinned = pinned.unwrap();
+    let now = Utc::now();
+    let unpin_at = unpin_after.and_then(|minutes| minutes_later(now, minutes));
+
+    if unpin_after.is_some() && unpin_at.is_none() {
+        error!("unpin_after of the scheduled message with id {schedule_id} is too large. The message is pinned but will not be unpinned by the bot");
+    }
+
+    pinned.add_new_pinned(PinnedMessage {
+        schedule_id,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        pinned_at: now,
+        unpin_at,
+    });
+
+    if let Err(e) = pinned.save_pinned_messages() {
+        error!("Failed to save pinned message data. The message from the scheduled message with id {schedule_id} is pinned but will not be unpinned by the bot. Reason: {e}");
+    }
+
+    Ok(())
+}
+
+/// The time a number of minutes after the given one. `None` if it is out of range
+pub fn minutes_later(time: DateTime<Utc>, minutes: u64) -> Option<DateTime<Utc>> {
+    let minutes = i64::try_from(minutes).ok()?;
+    time.checked_add_signed(TimeDelta::try_minutes(minutes)?)
+}
+
+/// Unpin all bot pinned messages whose `unpin_after` duration has passed
+pub async fn unpin_expired(ctx: &Context) {
+    let pinned_data = PinnedMessages::get_pinned_messages();
+
+    if let Err(e) = pinned_data {
+        error!("Failed to read pinned message data. Reason: {e}");
+        return;
+    }
+
+    let mut pinned = pinned_data.unwrap();
+    let expired = pinned.expired(Utc::now());
+
+    if expired.is_empty() {
+        return;
+    }
+
+    for target in &expired {
+        unpin_tracked(ctx, &mut pinned, target).await;
+    }
+
+    if let Err(e) = pinned.save_pinned_messages() {
+        error!("Failed to save pinned message data. Reason: {e}");
+    }
+}
+
+/// Unpin a message pinned by the bot and stop tracking it. If the message or the channel no
+/// longer exists, it is dropped from tracking. Other failures are kept to try again later
+async fn unpin_tracked(ctx: &Context, pinned: &mut PinnedMessages, target: &PinnedMessage) {
+    let result = target.channel_id.unpin(ctx, target.message_id).await;
+
+    match result {
+        Ok(()) => {
+            info!(
+                "Unpinned the message from the scheduled message with id {}",
+                target.schedule_id
+            );
+            pinned.remove_pinned(target.message_id);
+        }
+        Err(e) if is_not_found(&e) => {
+            error!("The pinned message from the scheduled message with id {} no longer exists. It will no longer be tracked. Reason: {e}", target.schedule_id);
+            pinned.remove_pinned(target.message_id);
+        }
+        Err(e) => {
+            error!("Failed to unpin the message from the scheduled message with id {}. Trying again later. Reason: {e}", target.schedule_id);
+        }
+    }
+}
+
+/// Whether a serenity error is caused by a user not accepting direct messages from the bot
+pub fn is_dm_closed(error: &SerenityError) -> bool {
+    if let SerenityError::Http(HttpError::UnsuccessfulRequest(response)) = error {
+        return response.error.code == DMS_CLOSED_CODE;
+    }
+    false
+}
+
+/// Whether a serenity error is caused by the bot missing the permissions for the request
+pub fn is_forbidden(error: &SerenityError) -> bool {
+    if let SerenityError::Http(e) = error {
+        return e.status_code() == Some(StatusCode::FORBIDDEN);
+    }
+    false
+}
+
+/// Whether a serenity error is caused by the target resource not existing on discord
+pub fn is_not_found(error: &SerenityError) -> bool {
+    if let SerenityError::Http(e) = error {
+        return e.status_code() == Some(StatusCode::NOT_FOUND);
+    }
+    false
+}
+
+/// Save the scheduled message id as completed. Tries 3 times and exits the bot if all of them fail
+pub async fn save_as_completed(ctx: &Context, completed: &mut CompletedScheduled, id: u32) {
+    completed.add_new_completed(id);
+    save_completed(ctx, completed).await;
+}
+
+/// Save a single target of a scheduled message with multiple targets, or a single recipient of its
+/// direct messages, as completed. Tries 3 times and exits the bot if all of them fail
+pub async fn save_target_as_completed(
+    ctx: &Context,
+    completed: &mut CompletedScheduled,
+    id: u32,
+    target: String,
+) {
+    completed.add_completed_target(id, target);
+    save_completed(ctx, completed).await;
+}
+
+async fn save_completed(ctx: &Context, completed: &mut CompletedScheduled) {
+    let lock = {
+        let data_read = ctx.data.read().await;
+        data_read.get::<CompletedLock>().unwrap().clone()
+    };
+    let _lock = lock.lock().await;
+
+    // Direct messages are sent in the background and save each recipient on their own, so
+    // anything saved since this data was read is kept
+    match CompletedScheduled::get_completed_scheduled() {
+        Ok(saved) => completed.merge(saved),
+        Err(e) => error!("Failed to read message completion data before saving it. Reason: {e}"),
+    }
+
+    for num in 0..3 {
+        let save_result = completed.save_completed_scheduled();
+        if save_result.is_ok() {
+            break;
+        }
+        if num == 2 {
+            error!("Failed to save the scheduled message id as completed. This is a fatal error and the bot will be exited. \
+                The scheduled message was sent successfully but the id number could not be saved as completed. \
+                Before the next run, completed.json must be updated manually otherwise the same scheduled message will be sent again.\n\nReason: {}", save_result.unwrap_err());
+            std::process::exit(1)
+        }
+        sleep(Duration::from_secs(2)).await;
+    }
 }
 
 /// Sleep for the remaining seconds in a minute
@@ -112,21 +658,12 @@ pub async fn sleep_remaining_time() {
     sleep(Duration::from_secs(seconds_remaining)).await;
 }
 
-/// Returns where a quiz data is saved in Context, highlighting whether a quiz is ongoing or not
-pub async fn quiz_ongoing(ctx: &Context) -> bool {
+/// The quiz that is currently ongoing, if any
+pub async fn get_ongoing_quiz(ctx: &Context) -> Option<QuizData> {
     let data_read = ctx.data.read().await;
     let data = data_read.get::<OngoingQuiz>().unwrap();
-    let config = data.lock().await;
-
-    config.is_some()
-}
-
-/// Get the target channel is to be monitored for a the quiz
-pub async fn get_monitor_channel_id(ctx: &Context) -> ChannelId {
-    let data_read = ctx.data.read().await;
-    let data = data_read.get::<OngoingQuiz>().unwrap();
-    let config = data.lock().await.clone().unwrap();
-    config.get_monitor_channel_id()
+    let quiz = data.lock().await.clone();
+    quiz
 }
 
 /// Saves a `QuizData` as ongoing with global access
@@ -156,6 +693,38 @@ pub fn contains_answer(content: Vec<&str>, answer: Vec<&str>) -> bool {
         .any(|window| window == answer.as_slice())
 }
 
+/// Mark the direct messages of the scheduled message as being sent. Returns false if they
+/// already are
+pub async fn start_sending_direct_messages(ctx: &Context, schedule_id: u32) -> bool {
+    get_sending_direct_messages(ctx)
+        .await
+        .lock()
+        .await
+        .insert(schedule_id)
+}
+
+/// Whether the direct messages of the scheduled message are being sent in the background
+pub async fn is_sending_direct_messages(ctx: &Context, schedule_id: u32) -> bool {
+    get_sending_direct_messages(ctx)
+        .await
+        .lock()
+        .await
+        .contains(&schedule_id)
+}
+
+pub async fn stop_sending_direct_messages(ctx: &Context, schedule_id: u32) {
+    get_sending_direct_messages(ctx)
+        .await
+        .lock()
+        .await
+        .remove(&schedule_id);
+}
+
+async fn get_sending_direct_messages(ctx: &Context) -> Arc<Mutex<HashSet<u32>>> {
+    let data_read = ctx.data.read().await;
+    data_read.get::<SendingDirectMessages>().unwrap().clone()
+}
+
 pub async fn is_thread_started(ctx: &Context) -> bool {
     let data_read = ctx.data.read().await;
     let data = data_read.get::<ThreadStarted>().unwrap();